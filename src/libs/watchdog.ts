@@ -0,0 +1,89 @@
+import type { Remote } from 'comlink';
+
+import type $ from './worker';
+import type { CompileInterrupted } from './worker';
+
+// ? Worker 内の中断はファイルの読み込み時にしか効かないため，評価中の無限ループなどは Worker ごと作り直す
+const COMPILE_METHODS = new Set(['svg', 'svgUncached', 'svgBatch', 'svgp', 'pdfr', 'svgr', 'pngr']);
+// ? Worker 側の制限時間に上乗せする猶予 (ms)
+const GRACE = 1000;
+
+export default class Watchdog {
+  timeout?: number;
+  cancelToken?: SharedArrayBuffer;
+
+  private pending = new Set<(reason: CompileInterrupted) => void>();
+  private timer?: number;
+  private restarting = false;
+  private restart: () => Promise<void>;
+
+  constructor(restart: () => Promise<void>) {
+    this.restart = restart;
+  }
+
+  wrap(remote: Remote<$>): Remote<$> {
+    return new Proxy(remote, {
+      get: (target, prop, receiver) => {
+        const value = Reflect.get(target, prop, receiver);
+        if (typeof prop !== 'string') return value;
+
+        if (prop === 'setCompileTimeout')
+          return (timeout?: number) => {
+            this.timeout = timeout;
+            return value(timeout);
+          };
+        if (prop === 'setCancelToken')
+          return (buffer?: SharedArrayBuffer) => {
+            this.cancelToken = buffer;
+            return value(buffer);
+          };
+        if (COMPILE_METHODS.has(prop)) return (...args: unknown[]) => this.run(() => value(...args));
+
+        return value;
+      },
+    });
+  }
+
+  // 作り直した Worker に設定を引き継ぐ
+  async restore(remote: Remote<$>) {
+    if (this.timeout !== undefined) await remote.setCompileTimeout(this.timeout);
+    if (this.cancelToken !== undefined) await remote.setCancelToken(this.cancelToken);
+  }
+
+  private run<T>(call: () => Promise<T>): Promise<T> {
+    return new Promise<T>((resolve, reject) => {
+      this.pending.add(reject);
+      this.kick();
+
+      call()
+        .then(resolve, reject)
+        .finally(() => {
+          this.pending.delete(reject);
+          this.kick();
+        });
+    });
+  }
+
+  // ? 最後に応答があってから timeout + GRACE の間，何も返ってこなければ固まっているとみなす
+  private kick() {
+    window.clearTimeout(this.timer);
+    if (!this.timeout || this.pending.size === 0) return;
+
+    this.timer = window.setTimeout(() => this.expire(), this.timeout + GRACE);
+  }
+
+  private async expire() {
+    const pending = [...this.pending];
+    this.pending.clear();
+
+    for (const reject of pending) reject({ interrupted: 'timeout', message: 'compilation timed out' });
+
+    if (this.restarting) return;
+    this.restarting = true;
+    try {
+      await this.restart();
+    } finally {
+      this.restarting = false;
+    }
+  }
+}
@@ -46,28 +46,101 @@ export default class $ {
     this.typst.set_offset(offset);
   }
 
+  setInlineCapacity(capacity: number): void {
+    this.typst.set_inline_capacity(capacity);
+  }
+
+  evict(kind: string, id: string): void {
+    this.typst.evict(kind, id);
+  }
+
+  setColorMapping(mapping: ColorMapping): void {
+    this.typst.set_color_mapping(mapping);
+  }
+
+  setOverflowMode(kind: string, mode: OverflowMode): void {
+    this.typst.set_overflow_mode(kind, mode);
+  }
+
+  // svg, svgp, pdfr, svgr, pngr の制限時間 (ms)
+  setCompileTimeout(timeout?: number): void {
+    this.typst.set_timeout(timeout);
+  }
+
+  // buffer[0] をコンパイル開始時から変えると実行中のコンパイルを中断する
+  setCancelToken(buffer?: SharedArrayBuffer): void {
+    this.typst.set_cancel_token(buffer ? new Int32Array(buffer) : undefined);
+  }
+
+  exportCache(): Uint8Array {
+    return this.typst.export_cache();
+  }
+
+  importCache(bytes: Uint8Array): void {
+    this.typst.import_cache(bytes);
+  }
+
   store(args: Args): void {
     this.typst.store(args.fonts ?? [], args.sources ?? [], args.files ?? []);
   }
 
-  svg(code: string, ndir: string, kind: string, id: string): SVGResult {
-    return this.typst.svg(code, ndir, kind, id);
+  svg(code: string, ndir: string, kind: string, id: string, alt?: AltOptions, idPrefix?: string): SVGResult {
+    return this.typst.svg(code, ndir, kind, id, alt, idPrefix);
   }
 
-  pdfr(ndir: string, filename: string, code: string, options: any): PdfrResult {
+  svgUncached(
+    code: string,
+    ndir: string,
+    kind: string,
+    id: string,
+    alt?: AltOptions,
+    idPrefix?: string,
+  ): SVGResult {
+    return this.typst.svg_uncached(code, ndir, kind, id, alt, idPrefix);
+  }
+
+  svgBatch(items: SVGBatchItem[], options?: SVGBatchOptions): SVGBatchResult {
+    return this.typst.svg_batch(items, options);
+  }
+
+  pdfr(ndir: string, filename: string, code: string | undefined, options: any): PdfrResult {
     return this.typst.pdfr(ndir, filename, code, options);
   }
 
-  svgr(ndir: string, filename: string, code: string, options: any): SvgrResult {
+  htmlr(ndir: string, filename: string, code: string | undefined, options: HtmlOptions): HtmlrResult {
+    return this.typst.htmlr(ndir, filename, code, options);
+  }
+
+  svgr(ndir: string, filename: string, code: string | undefined, options: any): SvgrResult {
     return this.typst.svgr(ndir, filename, code, options);
   }
 
-  pngr(ndir: string, filename: string, code: string, options: any): PngrResult {
+  pngr(ndir: string, filename: string, code: string | undefined, options: any): PngrResult {
     return this.typst.pngr(ndir, filename, code, options);
   }
 
-  svgp(ndir: string, filename: string, code: string): SVGPResult {
-    return this.typst.svgp(ndir, filename, code);
+  // code を省略すると edit で更新済みのソースを使う
+  svgp(
+    ndir: string,
+    filename: string,
+    code?: string,
+    alt?: AltOptions,
+    colors?: ColorMapping,
+    idPrefix?: string,
+  ): SVGPResult {
+    return this.typst.svgp(ndir, filename, code, alt, colors, idPrefix);
+  }
+
+  storeVaultFiles(paths: string[]): void {
+    this.typst.store_vault_files(paths);
+  }
+
+  storePackageIndex(index: PackageIndexEntry[]): void {
+    this.typst.store_package_index(index);
+  }
+
+  edit(path: string, from: number, to: number, text: string): void {
+    this.typst.edit(path, from, to, text);
   }
 
   listFonts(): FontInfo[] {
@@ -106,8 +179,28 @@ export default class $ {
     return this.typst.cetz_to_tikz(code);
   }
 
-  jumpFromClick(x: number, y: number): Jump | null {
-    return this.typst.jump_from_click(x, y);
+  mathml(code: string, display: boolean): MathMLResult {
+    return this.typst.mathml(code, display);
+  }
+
+  format(code: string, options: FormatOptions): FormatResult {
+    return this.typst.format(code, options);
+  }
+
+  semanticTokenLegend(): SemanticTokenLegend {
+    return this.typst.semantic_token_legend();
+  }
+
+  semanticTokens(code: string, mode: 'markup' | 'code' | 'math'): Uint32Array {
+    return this.typst.semantic_tokens(code, mode);
+  }
+
+  semanticTokensEdit(from: number, to: number, text: string): Uint32Array {
+    return this.typst.semantic_tokens_edit(from, to, text);
+  }
+
+  jumpFromClick(kind: string, id: string, x: number, y: number): Jump | null {
+    return this.typst.jump_from_click(kind, id, x, y);
   }
 
   jumpFromClickP(page: number, x: number, y: number): Jump | null {
@@ -118,6 +211,49 @@ export default class $ {
     return this.typst.jump_from_cursor_p(cursor);
   }
 
+  autocomplete(ndir: string, filename: string, code: string, cursor: number, explicit: boolean): Completions | null {
+    return this.typst.autocomplete(ndir, filename, code, cursor, explicit);
+  }
+
+  autocompleteInline(
+    code: string,
+    ndir: string,
+    kind: string,
+    id: string,
+    cursor: number,
+    explicit: boolean,
+  ): Completions | null {
+    return this.typst.autocomplete_inline(code, ndir, kind, id, cursor, explicit);
+  }
+
+  tooltip(cursor: number, side: 'before' | 'after'): Tooltip | null {
+    return this.typst.tooltip(cursor, side);
+  }
+
+  definition(cursor: number): Jump | null {
+    return this.typst.definition(cursor);
+  }
+
+  signatureHelp(cursor: number): Signature | null {
+    return this.typst.signature_help(cursor);
+  }
+
+  documentSymbols(): DocumentSymbol[] {
+    return this.typst.document_symbols();
+  }
+
+  listLabels(): Labels | null {
+    return this.typst.list_labels();
+  }
+
+  references(cursor: number): Reference[] | null {
+    return this.typst.references(cursor);
+  }
+
+  rename(cursor: number, newName: string): TextEdit[] {
+    return this.typst.rename(cursor, newName);
+  }
+
   fetch(path: string) {
     if (map.has(path)) {
       const v = map.get(path);
@@ -247,6 +383,13 @@ export interface PackageSpec {
   version: string;
 }
 
+export interface PackageIndexEntry {
+  namespace?: string;
+  name: string;
+  version: string;
+  description?: string;
+}
+
 export interface Diagnostic {
   severity: 'error' | 'warning';
   from: number;
@@ -257,14 +400,105 @@ export interface Diagnostic {
 
 export interface SVGResult {
   svg: string;
+  baseline: number;
+  height: number;
+  depth: number;
+  pages: string[];
   diags: Diagnostic[];
 }
 
+export type OverflowMode = 'first' | 'stitch' | 'pages';
+
+export interface ColorMapping {
+  palette?: { color: string; css: string }[];
+  invertLightness?: boolean;
+}
+
+export interface AltOptions {
+  mode: 'source' | 'latex' | 'speech';
+  source?: string;
+}
+
+export interface SVGBatchItem {
+  code: string;
+  ndir: string;
+  kind: string;
+  id: string;
+  alt?: AltOptions;
+  idPrefix?: string;
+}
+
+export interface SVGBatchOptions {
+  sharedDefs?: boolean;
+}
+
+export type SVGBatchItemResult =
+  | {
+      status: 'ok';
+      svg: string;
+      baseline: number;
+      height: number;
+      depth: number;
+      pages: string[];
+      diags: Diagnostic[];
+    }
+  | {
+      status: 'error';
+      message: string | null;
+      interrupted: 'cancelled' | 'timeout' | null;
+      diags: Diagnostic[];
+    };
+
+export interface SVGBatchResult {
+  items: SVGBatchItemResult[];
+  defs: string | null;
+}
+
+export interface MathMLResult {
+  mathml: string;
+  diags: Diagnostic[];
+}
+
+export interface FormatOptions {
+  indentWidth?: number;
+  maxWidth?: number;
+  mathOperatorSpacing?: boolean;
+  math?: boolean;
+}
+
+export interface FormatResult {
+  text: string;
+  errors: { message: string; from: number; to: number }[];
+}
+
+export interface SemanticTokenLegend {
+  types: string[];
+  modifiers: string[];
+}
+
+export interface CompileInterrupted {
+  interrupted: 'cancelled' | 'timeout';
+  message: string;
+}
+
+export function isInterrupted(e: unknown): e is CompileInterrupted {
+  return typeof e === 'object' && e !== null && 'interrupted' in e;
+}
+
 export interface PdfrResult {
   pdf: Uint8Array;
   diags: Diagnostic[];
 }
 
+export interface HtmlOptions {
+  mathSvg?: boolean;
+}
+
+export interface HtmlrResult {
+  html: string;
+  diags: Diagnostic[];
+}
+
 export interface SvgrResult {
   svgs: string[];
   diags: Diagnostic[];
@@ -311,6 +545,7 @@ export type Jump =
       package?: string;
       path: string;
       pos?: number;
+      range?: { start: number; end: number };
     }
   | {
       type: 'url';
@@ -322,3 +557,90 @@ export type Jump =
       x: number;
       y: number;
     };
+
+export interface Completion {
+  label: string;
+  kind: 'syntax' | 'func' | 'type' | 'param' | 'constant' | 'path' | 'package' | 'label' | 'font' | 'symbol';
+  apply?: string;
+  detail?: string;
+  symbol?: string;
+}
+
+export interface Completions {
+  from: number;
+  to: number;
+  completions: Completion[];
+}
+
+export type Tooltip =
+  | {
+      type: 'text';
+      text: string;
+    }
+  | {
+      type: 'code';
+      code: string;
+    };
+
+export interface SignatureParam {
+  name: string;
+  types: string[];
+  default?: string;
+  docs?: string;
+  positional: boolean;
+  named: boolean;
+  variadic: boolean;
+  required: boolean;
+}
+
+export interface Signature {
+  name?: string;
+  docs?: string;
+  params: SignatureParam[];
+  active?: number;
+}
+
+export interface DocumentSymbol {
+  name: string;
+  kind: 'heading' | 'function' | 'variable' | 'label' | 'figure' | 'equation';
+  level?: number;
+  number?: string;
+  page?: number;
+  from: number;
+  to: number;
+  children: DocumentSymbol[];
+}
+
+export interface Label {
+  name: string;
+  kind?: string;
+  detail?: string;
+  page?: number;
+  x?: number;
+  y?: number;
+}
+
+export interface Labels {
+  labels: Label[];
+  dangling: { name: string; from: number; to: number }[];
+}
+
+export interface Reference {
+  package?: string;
+  path: string;
+  from: number;
+  to: number;
+  definition: boolean;
+}
+
+export interface TextEdit {
+  path: string;
+  from: number;
+  to: number;
+  replacement: string;
+}
+
+export type RenameError =
+  | { kind: 'noTarget' }
+  | { kind: 'invalidName'; name: string }
+  | { kind: 'package'; package: string };
@@ -26,6 +26,7 @@ import { BASE_COLOR_VAR, TYPST_SVG_FILL, TYPST_SVG_STROKE } from './constants';
 import { DEFAULT_SETTINGS, type Settings } from './data/settings';
 import { collectRegions } from './editor/markdown/extensions/MarkdownCore';
 import TypstManager from './libs/typst';
+import Watchdog from './libs/watchdog';
 import type $ from './libs/worker';
 import Typst from './libs/worker';
 import TypstWorker from './libs/worker?worker&inline';
@@ -58,6 +59,7 @@ export default class ObsidianTypstMate extends Plugin {
   originalTex2chtml: any;
   typst!: $ | Remote<$>;
   worker?: Worker;
+  watchdog?: Watchdog;
   typstManager!: TypstManager;
 
   listeners: EventRef[] = [];
@@ -570,10 +572,22 @@ export default class ObsidianTypstMate extends Plugin {
     };
 
     if (this.settings.enableBackgroundRendering) {
-      this.worker = new TypstWorker();
-      const api = wrap<typeof $>(this.worker);
-      this.typst = await new api(this.localPackagesDirPaths, this.baseDirPath, Platform.isDesktopApp);
-      await this.typst.setMain(proxy(main));
+      const spawn = async () => {
+        this.worker = new TypstWorker();
+        const api = wrap<typeof $>(this.worker);
+        const remote = await new api(this.localPackagesDirPaths, this.baseDirPath, Platform.isDesktopApp);
+        await remote.setMain(proxy(main));
+        this.typst = this.watchdog!.wrap(remote);
+        return remote;
+      };
+      // 制限時間を超えて応答しない Worker を作り直す
+      this.watchdog = new Watchdog(async () => {
+        this.worker?.terminate();
+        const remote = await spawn();
+        await this.typstManager.init();
+        await this.watchdog!.restore(remote);
+      });
+      await spawn();
     } else {
       this.typst = new Typst(this.localPackagesDirPaths, this.baseDirPath, Platform.isDesktopApp);
       this.typst.setMain(main);
@@ -132,9 +132,12 @@ export default class TypstSVGElement extends TypstElement {
     const x = (event.clientX - rect.left) / (rect.width / svg.viewBox.baseVal.width);
     const y = (event.clientY - rect.top) / (rect.height / svg.viewBox.baseVal.height);
 
-    await this.plugin.typst.svg(this.format(), this.ndir, this.kind, this.id); // フレーム生成のための副作用
-
-    const result = await this.plugin.typst.jumpFromClick(x, y);
+    let result = await this.plugin.typst.jumpFromClick(this.kind, this.id, x, y);
+    if (!result) {
+      // キャッシュから追い出されている場合に備えて再生成する
+      await this.plugin.typst.svgUncached(this.format(), this.ndir, this.kind, this.id);
+      result = await this.plugin.typst.jumpFromClick(this.kind, this.id, x, y);
+    }
     if (result) {
       const view = this.plugin.app.workspace.getActiveFileView();
       if (!(view instanceof MarkdownView)) return;
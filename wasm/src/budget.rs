@@ -0,0 +1,109 @@
+use std::sync::Mutex;
+
+use js_sys::{Atomics, Date, Int32Array};
+use send_wrapper::SendWrapper;
+use serde::Serialize;
+
+use typst::diag::FileError;
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Interrupted {
+    Cancelled,
+    Timeout,
+}
+
+impl Interrupted {
+    pub fn message(self) -> &'static str {
+        match self {
+            Self::Cancelled => "compilation cancelled",
+            Self::Timeout => "compilation timed out",
+        }
+    }
+}
+
+impl From<Interrupted> for FileError {
+    fn from(interrupted: Interrupted) -> Self {
+        FileError::Other(Some(interrupted.message().into()))
+    }
+}
+
+// ? コンパイルの制限時間と中断トークン．
+// ? WasmWorld::source / file / font で確認する (評価はファイルを読み込むたび，レイアウトはフォントを引くたびに確認される)．
+// ? ファイルを読まない評価中の無限ループは止まらないため，TS 側の Watchdog が Worker ごと作り直す
+#[derive(Default)]
+pub struct Budget {
+    timeout: Option<f64>,
+    // ? JS 側が [0] を書き換えると，実行中のコンパイルを中断する (SharedArrayBuffer を想定)
+    token: Option<SendWrapper<Int32Array>>,
+
+    active: bool,
+    generation: i32,
+    deadline: Option<f64>,
+    interrupted: Mutex<Option<Interrupted>>,
+}
+
+impl Budget {
+    // ? ms．None で無制限
+    pub fn set_timeout(&mut self, timeout: Option<f64>) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_token(&mut self, token: Option<Int32Array>) {
+        self.token = token.map(SendWrapper::new);
+    }
+
+    pub fn start(&mut self) {
+        self.active = true;
+        self.generation = self.load_token().unwrap_or(0);
+        self.deadline = self.timeout.map(|timeout| Date::now() + timeout);
+        *self.interrupted.get_mut().unwrap() = None;
+    }
+
+    // ? 診断の変換などコンパイル外でのファイル読み込みを中断させない
+    pub fn pause(&mut self) {
+        self.active = false;
+    }
+
+    pub fn resume(&mut self) {
+        self.active = true;
+    }
+
+    // ? 中断されていればその理由を返す
+    pub fn finish(&mut self) -> Option<Interrupted> {
+        self.active = false;
+        self.deadline = None;
+        self.interrupted.get_mut().unwrap().take()
+    }
+
+    pub fn interrupted(&self) -> Option<Interrupted> {
+        *self.interrupted.lock().unwrap()
+    }
+
+    pub fn check(&self) -> Result<(), Interrupted> {
+        if !self.active {
+            return Ok(());
+        }
+
+        let mut interrupted = self.interrupted.lock().unwrap();
+        if interrupted.is_none() {
+            if self
+                .load_token()
+                .is_some_and(|generation| generation != self.generation)
+            {
+                *interrupted = Some(Interrupted::Cancelled);
+            } else if self.deadline.is_some_and(|deadline| Date::now() > deadline) {
+                *interrupted = Some(Interrupted::Timeout);
+            }
+        }
+
+        match *interrupted {
+            Some(reason) => Err(reason),
+            None => Ok(()),
+        }
+    }
+
+    fn load_token(&self) -> Option<i32> {
+        Atomics::load(self.token.as_ref()?, 0).ok()
+    }
+}
@@ -0,0 +1,159 @@
+use std::hash::{Hash, Hasher};
+
+use rustc_hash::{FxHashMap, FxHasher};
+use serde::{Deserialize, Serialize};
+
+use crate::inline::Metrics;
+
+const CACHE_VERSION: u32 = 3;
+const CACHE_CAPACITY: usize = 8192;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CachedSvg {
+    pub svg: String,
+    pub metrics: Metrics,
+    #[serde(default)]
+    pub pages: Vec<String>,
+}
+
+// ? deps は読み込まれたローカルファイルのパスとハッシュ
+#[derive(Serialize, Deserialize, Clone)]
+struct CacheEntry {
+    svg: CachedSvg,
+    deps: Vec<(String, u64)>,
+    #[serde(skip)]
+    used: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    // ? 古い順
+    entries: Vec<(u64, CacheEntry)>,
+}
+
+// ? 警告のない SVG の結果のみを保持する LRU キャッシュ
+pub struct ResultCache {
+    fontsize: f64,
+    inputs: FxHashMap<String, u64>,
+    fingerprint: u64,
+    tick: u64,
+    entries: FxHashMap<u64, CacheEntry>,
+}
+
+impl ResultCache {
+    pub fn new(fontsize: f64) -> Self {
+        Self {
+            fontsize,
+            inputs: FxHashMap::default(),
+            fingerprint: 0,
+            tick: 0,
+            entries: FxHashMap::default(),
+        }
+    }
+
+    // ? 出力に影響しうるフォントやファイルを記録する (順序に依存しない)
+    pub fn record(&mut self, name: String, data: &[u8]) {
+        let hash = hash_of(data);
+        if let Some(old) = self.inputs.insert(name, hash) {
+            self.fingerprint = self.fingerprint.wrapping_sub(old);
+        }
+        self.fingerprint = self.fingerprint.wrapping_add(hash);
+    }
+
+    pub fn record_font(&mut self, data: &[u8]) {
+        self.record(format!("font:{:016x}", hash_of(data)), data);
+    }
+
+    // ? ndir は相対パスの import の解決に影響する
+    pub fn key(&self, code: &str, ndir: &str, kind: &str, offset: f64) -> u64 {
+        let mut hasher = FxHasher::default();
+        code.hash(&mut hasher);
+        ndir.hash(&mut hasher);
+        kind.hash(&mut hasher);
+        self.fontsize.to_bits().hash(&mut hasher);
+        offset.to_bits().hash(&mut hasher);
+        self.fingerprint.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // ? 依存ファイルのハッシュが hash と一致しなければ破棄する
+    pub fn get(&mut self, key: u64, hash: impl Fn(&str) -> Option<u64>) -> Option<&CachedSvg> {
+        let valid = self
+            .entries
+            .get(&key)?
+            .deps
+            .iter()
+            .all(|(path, expected)| hash(path) == Some(*expected));
+        if !valid {
+            self.entries.remove(&key);
+            return None;
+        }
+
+        self.tick += 1;
+        let entry = self.entries.get_mut(&key)?;
+        entry.used = self.tick;
+
+        Some(&entry.svg)
+    }
+
+    pub fn insert(&mut self, key: u64, cached: CachedSvg, deps: Vec<(String, u64)>) {
+        self.tick += 1;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                svg: cached,
+                deps,
+                used: self.tick,
+            },
+        );
+        self.shrink();
+    }
+
+    // ? 容量を超えたら古いものから 1/4 をまとめて追い出す
+    fn shrink(&mut self) {
+        if self.entries.len() <= CACHE_CAPACITY {
+            return;
+        }
+
+        let mut used: Vec<u64> = self.entries.values().map(|entry| entry.used).collect();
+        used.sort_unstable();
+        let threshold = used[self.entries.len() - CACHE_CAPACITY * 3 / 4];
+        self.entries.retain(|_, entry| entry.used >= threshold);
+    }
+
+    pub fn export(&self) -> Vec<u8> {
+        let mut entries: Vec<(u64, CacheEntry)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (*key, entry.clone()))
+            .collect();
+        entries.sort_by_key(|(_, entry)| entry.used);
+
+        let file = CacheFile {
+            version: CACHE_VERSION,
+            entries,
+        };
+
+        serde_json::to_vec(&file).unwrap_or_default()
+    }
+
+    pub fn import(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let file: CacheFile = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+        if file.version != CACHE_VERSION {
+            return Err(format!("unsupported cache version: {}", file.version));
+        }
+
+        for (key, entry) in file.entries {
+            self.insert(key, entry.svg, entry.deps);
+        }
+
+        Ok(())
+    }
+}
+
+pub fn hash_of(data: &[u8]) -> u64 {
+    let mut hasher = FxHasher::default();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,181 @@
+use std::str::FromStr;
+
+use typst::{
+    foundations::Smart,
+    layout::{Frame, FrameItem, Page},
+    visualize::{Color, FixedStroke, Paint},
+};
+
+// ? パレットの色を一時的に置き換える色 (#017eXX)．SVG 化した後に CSS の値へ置換する
+const SENTINEL: [u8; 2] = [0x01, 0x7e];
+const MAX_PALETTE: usize = 256;
+
+pub struct ColorMapping {
+    palette: Vec<(Color, String)>,
+    invert_lightness: bool,
+}
+
+impl ColorMapping {
+    pub fn new(palette: Vec<(Color, String)>, invert_lightness: bool) -> Self {
+        let mut palette = palette;
+        palette.truncate(MAX_PALETTE);
+        Self {
+            palette,
+            invert_lightness,
+        }
+    }
+
+    // ? 従来どおり黒だけを --typst-base-color にする
+    pub fn base() -> Self {
+        Self::new(
+            vec![(Color::BLACK, "var(--typst-base-color)".to_string())],
+            false,
+        )
+    }
+
+    fn map(&self, color: Color) -> Color {
+        let rgba = color.to_vec4_u8();
+        if rgba[3] == 255
+            && let Some(index) = self
+                .palette
+                .iter()
+                .position(|(c, _)| c.to_vec4_u8() == rgba)
+        {
+            return Color::from_u8(SENTINEL[0], SENTINEL[1], index as u8, 255);
+        }
+
+        if self.invert_lightness {
+            invert_lightness(color)
+        } else {
+            color
+        }
+    }
+
+    pub fn apply(&self, page: &Page) -> Page {
+        let mut page = page.clone();
+        page.frame = map_frame(&page.frame, &|color| self.map(color));
+        if let Some(Paint::Solid(color)) = page.fill_or_transparent() {
+            page.fill = Smart::Custom(Some(Paint::Solid(self.map(color))));
+        }
+        page
+    }
+
+    pub fn finish(&self, svg: String) -> String {
+        let mut svg = svg;
+        for (index, (_, css)) in self.palette.iter().enumerate() {
+            let sentinel = format!("#{:02x}{:02x}{:02x}", SENTINEL[0], SENTINEL[1], index);
+            svg = svg.replace(&sentinel, css);
+        }
+        svg
+    }
+}
+
+// ? OKLab の明度を反転する
+fn invert_lightness(color: Color) -> Color {
+    let [r, g, b, a] = color.to_vec4_u8();
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(r), linear(g), linear(b));
+
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    let lightness = 0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s;
+    let ok_a = 1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s;
+    let ok_b = 0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s;
+
+    let lightness = 1.0 - lightness;
+    let l = (lightness + 0.3963377774 * ok_a + 0.2158037573 * ok_b).powi(3);
+    let m = (lightness - 0.1055613458 * ok_a - 0.0638541728 * ok_b).powi(3);
+    let s = (lightness - 0.0894841775 * ok_a - 1.2914855480 * ok_b).powi(3);
+
+    let gamma = |c: f64| {
+        let c = if c <= 0.0031308 {
+            12.92 * c
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (c.clamp(0.0, 1.0) * 255.0).round() as u8
+    };
+
+    Color::from_u8(
+        gamma(4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s),
+        gamma(-1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s),
+        gamma(-0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s),
+        a,
+    )
+}
+
+pub enum Background {
+    Transparent,
+    Page,
+    Color(Color),
+}
+
+pub fn parse_background(value: &str) -> Result<Background, String> {
+    match value {
+        "transparent" => Ok(Background::Transparent),
+        "page" => Ok(Background::Page),
+        color => parse_color(color).map(Background::Color),
+    }
+}
+
+pub fn parse_color(value: &str) -> Result<Color, String> {
+    Color::from_str(value).map_err(|e| format!("invalid color {}: {}", value, e))
+}
+
+pub fn is_black(color: Color) -> bool {
+    color.to_vec4_u8() == [0, 0, 0, 255]
+}
+
+// ? 単色の塗りと線だけを置き換える (画像やグラデーションはそのまま)
+pub fn map_frame(frame: &Frame, f: &impl Fn(Color) -> Color) -> Frame {
+    let mut out = frame.clone();
+    out.clear();
+
+    for (pos, item) in frame.items() {
+        let item = match item {
+            FrameItem::Group(group) => {
+                let mut group = group.clone();
+                group.frame = map_frame(&group.frame, f);
+                FrameItem::Group(group)
+            }
+            FrameItem::Text(text) => {
+                let mut text = text.clone();
+                text.fill = map_paint(&text.fill, f);
+                text.stroke = text.stroke.map(|stroke| map_stroke(stroke, f));
+                FrameItem::Text(text)
+            }
+            FrameItem::Shape(shape, span) => {
+                let mut shape = shape.clone();
+                shape.fill = shape.fill.map(|paint| map_paint(&paint, f));
+                shape.stroke = shape.stroke.map(|stroke| map_stroke(stroke, f));
+                FrameItem::Shape(shape, *span)
+            }
+            other => other.clone(),
+        };
+        out.push(*pos, item);
+    }
+
+    out
+}
+
+fn map_paint(paint: &Paint, f: &impl Fn(Color) -> Color) -> Paint {
+    match paint {
+        Paint::Solid(color) => Paint::Solid(f(*color)),
+        other => other.clone(),
+    }
+}
+
+fn map_stroke(stroke: FixedStroke, f: &impl Fn(Color) -> Color) -> FixedStroke {
+    FixedStroke {
+        paint: map_paint(&stroke.paint, f),
+        ..stroke
+    }
+}
@@ -0,0 +1,261 @@
+use serde::Deserialize;
+use tylax::{typst_document_to_latex, typst_to_latex};
+
+use typst::syntax::{LinkedNode, SyntaxKind, SyntaxNode, parse, parse_math};
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum AltMode {
+    Source,
+    Latex,
+    Speech,
+}
+
+// ? 読み上げ用の記号
+const SPOKEN: &[(&str, &str)] = &[
+    ("+", "plus"),
+    ("-", "minus"),
+    ("−", "minus"),
+    ("*", "times"),
+    ("×", "times"),
+    ("·", "dot"),
+    ("=", "equals"),
+    ("!=", "not equal to"),
+    ("≠", "not equal to"),
+    ("<", "less than"),
+    (">", "greater than"),
+    ("<=", "less than or equal to"),
+    ("≤", "less than or equal to"),
+    (">=", "greater than or equal to"),
+    ("≥", "greater than or equal to"),
+    ("->", "right arrow"),
+    ("→", "right arrow"),
+    ("=>", "implies"),
+    ("⇒", "implies"),
+    ("<-", "left arrow"),
+    ("←", "left arrow"),
+    ("<=>", "if and only if"),
+    ("⇔", "if and only if"),
+    ("...", "dots"),
+    ("…", "dots"),
+    ("(", "open paren"),
+    (")", "close paren"),
+    ("[", "open bracket"),
+    ("]", "close bracket"),
+    ("{", "open brace"),
+    ("}", "close brace"),
+    ("|", "bar"),
+    ("'", "prime"),
+    ("!", "factorial"),
+];
+
+// ? math は code が数式の中身かどうか
+pub fn describe(code: &str, mode: AltMode, math: bool) -> String {
+    match (mode, math) {
+        (AltMode::Source, _) => code.trim().to_string(),
+        (AltMode::Latex, true) => typst_to_latex(code),
+        (AltMode::Latex, false) => typst_document_to_latex(code),
+        (AltMode::Speech, true) => speak_root(&LinkedNode::new(&parse_math(code))),
+        (AltMode::Speech, false) => speak_root(&LinkedNode::new(&parse(code))),
+    }
+}
+
+// ? プリアンブルやテンプレートで包まれたコードから，最後の数式の中身を取り出す
+pub fn formula(code: &str) -> Option<String> {
+    fn last_equation(node: &SyntaxNode) -> Option<&SyntaxNode> {
+        node.children().rev().find_map(|child| {
+            last_equation(child).or((child.kind() == SyntaxKind::Equation).then_some(child))
+        })
+    }
+
+    let root = parse(code);
+    let body = last_equation(&root)?
+        .children()
+        .find(|child| child.kind() == SyntaxKind::Math)?
+        .clone()
+        .into_text();
+
+    Some(body.trim().to_string())
+}
+
+// ? <svg> に role, aria-label, <title> を付ける
+pub fn embed(svg: &str, description: &str) -> String {
+    let Some(start) = svg.find("<svg") else {
+        return svg.to_string();
+    };
+    let Some(end) = svg[start..].find('>').map(|i| start + i) else {
+        return svg.to_string();
+    };
+    let escaped = escape(description);
+
+    format!(
+        "{} role=\"img\" aria-label=\"{}\"><title>{}</title>{}",
+        &svg[..end],
+        escaped,
+        escaped,
+        &svg[end + 1..]
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\n' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn speak_root(root: &LinkedNode) -> String {
+    let mut words = Vec::new();
+    speak(root, &mut words);
+
+    words
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn speak(node: &LinkedNode, out: &mut Vec<String>) {
+    match node.kind() {
+        SyntaxKind::MathFrac => {
+            let mut parts = node.children().filter(|c| !c.kind().is_trivia());
+            if let (Some(num), _, Some(denom)) = (parts.next(), parts.next(), parts.next()) {
+                speak_group(&num, out);
+                out.push("over".into());
+                speak_group(&denom, out);
+            }
+        }
+        SyntaxKind::MathAttach => {
+            let mut children = node.children().filter(|c| !c.kind().is_trivia());
+            if let Some(base) = children.next() {
+                speak(&base, out);
+            }
+            while let (Some(marker), Some(script)) = (children.next(), children.next()) {
+                match (marker.kind(), script.text().as_str()) {
+                    (SyntaxKind::Hat, "2") => out.push("squared".into()),
+                    (SyntaxKind::Hat, "3") => out.push("cubed".into()),
+                    (SyntaxKind::Hat, _) => {
+                        out.push("to the power of".into());
+                        speak_group(&script, out);
+                    }
+                    _ => {
+                        out.push("sub".into());
+                        speak_group(&script, out);
+                    }
+                }
+            }
+        }
+        SyntaxKind::MathRoot => {
+            let symbol = node
+                .children()
+                .next()
+                .map(|c| c.text().to_string())
+                .unwrap_or_default();
+            out.push(
+                match symbol.as_str() {
+                    "∛" => "cube root of",
+                    "∜" => "fourth root of",
+                    _ => "square root of",
+                }
+                .into(),
+            );
+            for child in node.children().skip(1) {
+                speak(&child, out);
+            }
+        }
+        SyntaxKind::MathPrimes => out.push("prime".into()),
+        SyntaxKind::FuncCall => {
+            let mut children = node.children();
+            let callee = children
+                .next()
+                .map(|c| c.get().clone().into_text().to_string())
+                .unwrap_or_default();
+            let args: Vec<LinkedNode> = children
+                .flat_map(|args| args.children().collect::<Vec<_>>())
+                .filter(|c| {
+                    !c.kind().is_trivia()
+                        && !matches!(
+                            c.kind(),
+                            SyntaxKind::LeftParen | SyntaxKind::RightParen | SyntaxKind::Comma
+                        )
+                })
+                .collect();
+
+            match (callee.as_str(), args.as_slice()) {
+                ("frac", [num, denom]) => {
+                    speak_group(num, out);
+                    out.push("over".into());
+                    speak_group(denom, out);
+                }
+                ("sqrt", [radicand]) => {
+                    out.push("square root of".into());
+                    speak_group(radicand, out);
+                }
+                ("root", [index, radicand]) => {
+                    speak_group(index, out);
+                    out.push("root of".into());
+                    speak_group(radicand, out);
+                }
+                _ => {
+                    out.push(callee.replace('.', " "));
+                    if !args.is_empty() {
+                        out.push("of".into());
+                    }
+                    for (i, arg) in args.iter().enumerate() {
+                        if i > 0 {
+                            out.push("and".into());
+                        }
+                        speak_group(arg, out);
+                    }
+                }
+            }
+        }
+        SyntaxKind::FieldAccess => out.push(node.get().clone().into_text().replace('.', " ")),
+        SyntaxKind::Str => out.push(node.text().trim_matches('"').to_string()),
+        SyntaxKind::Hash
+        | SyntaxKind::Dollar
+        | SyntaxKind::Star
+        | SyntaxKind::Underscore
+        | SyntaxKind::HeadingMarker
+        | SyntaxKind::ListMarker
+        | SyntaxKind::EnumMarker
+        | SyntaxKind::TermMarker
+        | SyntaxKind::LineComment
+        | SyntaxKind::BlockComment
+        | SyntaxKind::MathAlignPoint => {}
+        _ if node.children().len() == 0 => {
+            let text = node.text();
+            match SPOKEN.iter().find(|(symbol, _)| *symbol == text.as_str()) {
+                Some((_, word)) => out.push(word.to_string()),
+                None => out.push(text.to_string()),
+            }
+        }
+        _ => {
+            for child in node.children() {
+                speak(&child, out);
+            }
+        }
+    }
+}
+
+// ? 括弧で囲まれたグループは括弧を読まない
+fn speak_group(node: &LinkedNode, out: &mut Vec<String>) {
+    if node.kind() == SyntaxKind::MathDelimited {
+        let children: Vec<LinkedNode> = node.children().collect();
+        if children.len() >= 2 {
+            for child in &children[1..children.len() - 1] {
+                speak(child, out);
+            }
+            return;
+        }
+    }
+    speak(node, out);
+}
@@ -0,0 +1,513 @@
+use std::ops::Range;
+
+use typst::syntax::{LinkedNode, SyntaxKind, SyntaxNode, parse, parse_math};
+
+pub struct FormatOptions {
+    pub indent_width: usize,
+    pub max_width: usize,
+    pub math_operator_spacing: bool,
+}
+
+pub struct FormatError {
+    pub message: String,
+    pub range: Range<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Markup,
+    Code,
+    Math,
+}
+
+// ? 数式中で前後に空白を入れる演算子
+const MATH_OPERATORS: &[&str] = &[
+    "=", "+", "-", "<", ">", "!=", "<=", ">=", "==", ":=", "=:", "::=", "->", "=>", "<-", "<=>",
+    "<==>", "-->", "<--", "~", "≠", "≤", "≥", "≈", "≡", "→", "⇒", "←", "⇔",
+];
+
+pub fn format_markup(text: &str, options: &FormatOptions) -> Result<String, Vec<FormatError>> {
+    format_root(&parse(text), Mode::Markup, options)
+}
+
+pub fn format_math(text: &str, options: &FormatOptions) -> Result<String, Vec<FormatError>> {
+    format_root(&parse_math(text), Mode::Math, options)
+}
+
+fn format_root(
+    root: &SyntaxNode,
+    mode: Mode,
+    options: &FormatOptions,
+) -> Result<String, Vec<FormatError>> {
+    if root.erroneous() {
+        let mut errors = Vec::new();
+        collect_errors(&LinkedNode::new(root), &mut errors);
+        return Err(errors);
+    }
+
+    let mut printer = Printer {
+        out: String::with_capacity(root.len()),
+        options,
+        scripts: 0,
+    };
+    printer.node(root, mode);
+
+    Ok(printer.out)
+}
+
+fn collect_errors(node: &LinkedNode, out: &mut Vec<FormatError>) {
+    if node.kind() == SyntaxKind::Error {
+        for error in node.errors() {
+            out.push(FormatError {
+                message: error.message.to_string(),
+                range: node.range(),
+            });
+        }
+    }
+
+    for child in node.children() {
+        collect_errors(&child, out);
+    }
+}
+
+struct Printer<'a> {
+    out: String,
+    options: &'a FormatOptions,
+    scripts: usize,
+}
+
+impl Printer<'_> {
+    fn node(&mut self, node: &SyntaxNode, mode: Mode) {
+        match node.kind() {
+            SyntaxKind::Raw | SyntaxKind::Str => self.verbatim(node),
+            SyntaxKind::Markup => self.sequence(node, Mode::Markup),
+            SyntaxKind::Math => self.sequence(node, Mode::Math),
+            SyntaxKind::Equation => self.equation(node),
+            SyntaxKind::CodeBlock => self.code_block(node),
+            // ? 添字や分数の中は詰めて書く
+            SyntaxKind::MathAttach | SyntaxKind::MathFrac | SyntaxKind::MathRoot => {
+                self.scripts += 1;
+                self.generic(node, mode);
+                self.scripts -= 1;
+            }
+            SyntaxKind::Args
+            | SyntaxKind::Array
+            | SyntaxKind::Dict
+            | SyntaxKind::Params
+            | SyntaxKind::Destructuring
+                if mode == Mode::Code =>
+            {
+                self.group(node)
+            }
+            _ if node.children().len() == 0 => self.out.push_str(node.text()),
+            _ => self.generic(node, mode),
+        }
+    }
+
+    fn verbatim(&mut self, node: &SyntaxNode) {
+        self.out.push_str(&node.clone().into_text());
+    }
+
+    // ? Markup / Math の直下: `#` の後は Code として扱う
+    fn sequence(&mut self, node: &SyntaxNode, mode: Mode) {
+        let children: Vec<&SyntaxNode> = node.children().collect();
+        let spacing = mode == Mode::Math && self.options.math_operator_spacing && self.scripts == 0;
+
+        let mut embedded = false;
+        for (i, child) in children.iter().enumerate() {
+            match child.kind() {
+                SyntaxKind::Space | SyntaxKind::Parbreak => {
+                    let text = child.text();
+                    if text.contains('\n') {
+                        self.newlines(text);
+                    } else if mode == Mode::Math {
+                        self.out.push(' ');
+                    } else {
+                        self.out.push_str(text);
+                    }
+                }
+                _ if embedded => {
+                    self.node(child, Mode::Code);
+                    embedded = false;
+                }
+                SyntaxKind::Hash => {
+                    self.out.push('#');
+                    embedded = true;
+                }
+                _ if spacing && is_math_operator(&children, i) => {
+                    if i > 0 && children[i - 1].kind() != SyntaxKind::MathAlignPoint {
+                        self.ensure_space();
+                    }
+                    self.out.push_str(child.text());
+                    if let Some(next) = children.get(i + 1)
+                        && !matches!(next.kind(), SyntaxKind::Space | SyntaxKind::MathAlignPoint)
+                    {
+                        self.out.push(' ');
+                    }
+                }
+                _ => self.node(child, mode),
+            }
+        }
+    }
+
+    fn equation(&mut self, node: &SyntaxNode) {
+        for child in node.children() {
+            match child.kind() {
+                // ? `$ x $` と `$x$` の区別は保つ
+                SyntaxKind::Space if child.text().contains('\n') => self.newlines(child.text()),
+                SyntaxKind::Space => self.out.push(' '),
+                _ => self.node(child, Mode::Math),
+            }
+        }
+    }
+
+    fn code_block(&mut self, node: &SyntaxNode) {
+        let mut items: Vec<&SyntaxNode> = Vec::new();
+        for child in node.children() {
+            match child.kind() {
+                SyntaxKind::LeftBrace | SyntaxKind::RightBrace => {}
+                SyntaxKind::Code => items.extend(child.children()),
+                _ => items.push(child),
+            }
+        }
+
+        let multiline = node.clone().into_text().contains('\n');
+        let base = self.line_indent();
+        let inner = base + self.options.indent_width;
+
+        self.out.push('{');
+        let mut gap: Option<&str> = None;
+        let mut first = true;
+        for item in items {
+            if item.kind() == SyntaxKind::Space {
+                gap = Some(item.text());
+                continue;
+            }
+
+            let breaks = gap.map_or(0, |g| g.matches('\n').count());
+            if multiline && (first || breaks > 0) {
+                if !first && breaks > 1 {
+                    self.out.push('\n');
+                }
+                self.out.push('\n');
+                self.indent(inner);
+            } else if item.kind() != SyntaxKind::Semicolon {
+                self.out.push(' ');
+            }
+
+            self.node(item, Mode::Code);
+            gap = None;
+            first = false;
+        }
+
+        if !first {
+            if multiline {
+                self.out.push('\n');
+                self.indent(base);
+            } else {
+                self.out.push(' ');
+            }
+        }
+        self.out.push('}');
+    }
+
+    fn group(&mut self, node: &SyntaxNode) {
+        let children: Vec<&SyntaxNode> = node.children().collect();
+        let Some(open) = children
+            .iter()
+            .position(|c| c.kind() == SyntaxKind::LeftParen)
+        else {
+            return self.generic(node, Mode::Code);
+        };
+        let Some(close) = children
+            .iter()
+            .position(|c| c.kind() == SyntaxKind::RightParen)
+        else {
+            return self.generic(node, Mode::Code);
+        };
+
+        let inner = &children[open + 1..close];
+        if inner
+            .iter()
+            .any(|c| c.kind().is_trivia() && c.kind() != SyntaxKind::Space)
+        {
+            return self.generic(node, Mode::Code);
+        }
+
+        let items: Vec<&SyntaxNode> = inner
+            .iter()
+            .copied()
+            .filter(|c| !matches!(c.kind(), SyntaxKind::Space | SyntaxKind::Comma))
+            .collect();
+
+        for child in &children[..open] {
+            self.node(child, Mode::Code);
+        }
+
+        if items.len() == 1 && items[0].kind() == SyntaxKind::Colon {
+            self.out.push_str("(:)");
+        } else if items.is_empty() {
+            self.out.push_str("()");
+        } else {
+            let keep_comma = items.len() == 1
+                && matches!(node.kind(), SyntaxKind::Array | SyntaxKind::Destructuring);
+
+            let mark = self.out.len();
+            // ? 1 行に収まる幅の下限 (空白とカンマ以外は整形で消えない)
+            let estimate: usize = items
+                .iter()
+                .map(|c| {
+                    let text = (*c).clone().into_text();
+                    text.chars()
+                        .filter(|c| !c.is_whitespace() && *c != ',')
+                        .count()
+                        + 2
+                })
+                .sum();
+            let mut fits = self.line_width() + estimate <= self.options.max_width;
+            if fits {
+                self.out.push('(');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.node(item, Mode::Code);
+                }
+                if keep_comma {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+
+                fits =
+                    !self.out[mark..].contains('\n') && self.line_width() <= self.options.max_width;
+                if !fits {
+                    self.out.truncate(mark);
+                }
+            }
+
+            if !fits {
+                let base = self.line_indent();
+                self.out.push('(');
+                for item in &items {
+                    self.out.push('\n');
+                    self.indent(base + self.options.indent_width);
+                    self.node(item, Mode::Code);
+                    self.out.push(',');
+                }
+                self.out.push('\n');
+                self.indent(base);
+                self.out.push(')');
+            }
+        }
+
+        for child in &children[close + 1..] {
+            self.node(child, Mode::Code);
+        }
+    }
+
+    fn generic(&mut self, node: &SyntaxNode, mode: Mode) {
+        let children: Vec<&SyntaxNode> = node.children().collect();
+
+        let mut prev: Option<&SyntaxNode> = None;
+        let mut gap: Option<&str> = None;
+        for child in children {
+            if child.kind() == SyntaxKind::Space {
+                gap = Some(child.text());
+                continue;
+            }
+
+            match gap {
+                Some(g) if g.contains('\n') => self.newlines(g),
+                Some(g) if mode == Mode::Markup => self.out.push_str(g),
+                Some(_) if mode == Mode::Code && no_space_before(child) => {}
+                Some(_) => self.out.push(' '),
+                None if mode == Mode::Code
+                    && prev.is_some_and(|p| space_between(node, p, child)) =>
+                {
+                    self.out.push(' ')
+                }
+                None => {}
+            }
+
+            self.node(child, mode);
+            prev = Some(child);
+            gap = None;
+        }
+
+        if let Some(g) = gap {
+            if g.contains('\n') {
+                self.newlines(g);
+            } else {
+                self.out
+                    .push_str(if mode == Mode::Markup { g } else { " " });
+            }
+        }
+    }
+
+    // ? 行末の空白を取り除き，インデントは残す
+    fn newlines(&mut self, text: &str) {
+        while self.out.ends_with([' ', '\t']) {
+            self.out.pop();
+        }
+
+        let mut lines = text.split('\n');
+        lines.next();
+        let rest: Vec<&str> = lines.collect();
+        for (i, line) in rest.iter().enumerate() {
+            self.out.push('\n');
+            if i == rest.len() - 1 {
+                self.out.push_str(line);
+            }
+        }
+    }
+
+    fn ensure_space(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with([' ', '\n']) {
+            self.out.push(' ');
+        }
+    }
+
+    fn indent(&mut self, width: usize) {
+        self.out.extend(std::iter::repeat_n(' ', width));
+    }
+
+    fn current_line(&self) -> &str {
+        match self.out.rfind('\n') {
+            Some(i) => &self.out[i + 1..],
+            None => &self.out,
+        }
+    }
+
+    fn line_width(&self) -> usize {
+        self.current_line().chars().count()
+    }
+
+    fn line_indent(&self) -> usize {
+        self.current_line()
+            .chars()
+            .take_while(|c| *c == ' ')
+            .count()
+    }
+}
+
+fn no_space_before(next: &SyntaxNode) -> bool {
+    matches!(
+        next.kind(),
+        SyntaxKind::Comma | SyntaxKind::Semicolon | SyntaxKind::Colon
+    )
+}
+
+fn space_between(parent: &SyntaxNode, prev: &SyntaxNode, next: &SyntaxNode) -> bool {
+    if parent.kind() == SyntaxKind::Binary {
+        return true;
+    }
+
+    matches!(
+        prev.kind(),
+        SyntaxKind::Comma | SyntaxKind::Colon | SyntaxKind::Eq | SyntaxKind::Arrow
+    ) || matches!(next.kind(), SyntaxKind::Eq | SyntaxKind::Arrow)
+}
+
+fn is_math_operator(children: &[&SyntaxNode], i: usize) -> bool {
+    let child = children[i];
+    if child.children().len() > 0 || !MATH_OPERATORS.contains(&child.text().as_str()) {
+        return false;
+    }
+
+    // ? 前置の負号は除く
+    if matches!(child.text().as_str(), "-" | "+") {
+        let prev = children[..i]
+            .iter()
+            .rev()
+            .find(|c| c.kind() != SyntaxKind::Space);
+        return match prev {
+            None => false,
+            Some(p) => {
+                !matches!(
+                    p.kind(),
+                    SyntaxKind::MathAlignPoint | SyntaxKind::Linebreak | SyntaxKind::Comma
+                ) && !MATH_OPERATORS.contains(&p.text().as_str())
+            }
+        };
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serde::format::FormatResultSer;
+
+    fn options() -> FormatOptions {
+        FormatOptions {
+            indent_width: 2,
+            max_width: 80,
+            math_operator_spacing: true,
+        }
+    }
+
+    fn markup(text: &str) -> String {
+        format_markup(text, &options()).ok().unwrap()
+    }
+
+    fn math(text: &str) -> String {
+        format_math(text, &options()).ok().unwrap()
+    }
+
+    #[test]
+    fn idempotent() {
+        let samples = [
+            "= Title\n\n#let f(x,y) = x+y\n#f(1,2)   \n\n$a+b=c$ and $ x^(a+b) <= -y $\n",
+            "#{\n    let xs = (1,2,3)\n\n\n    for x in xs { x }\n}\n",
+            "#set text(font: \"New Computer Modern\",size: 10pt,lang: \"ja\",region: \"jp\",weight: \"regular\")\n",
+        ];
+        for sample in samples {
+            let once = markup(sample);
+            assert_eq!(markup(&once), once, "{sample:?}");
+        }
+
+        let once = math("sum_(i=1)^n a_i=b & =>c\\\n -x");
+        assert_eq!(math(&once), once);
+    }
+
+    #[test]
+    fn keeps_comments_in_code_block() {
+        let text = "#{\n  let x = 1 // note\n  /* block */\n  x\n}";
+        assert_eq!(markup(text), text);
+    }
+
+    #[test]
+    fn keeps_comments_in_args() {
+        let text = "#f(a, /* c */ b)";
+        assert_eq!(markup(text), text);
+
+        let text = "#f(\n  a, // c\n  b,\n)";
+        assert_eq!(markup(text), text);
+    }
+
+    #[test]
+    fn math_operator_spacing() {
+        assert_eq!(math("a+b=c"), "a + b = c");
+        assert_eq!(math("a = -b"), "a = -b");
+        // ? 添字の中は詰めたまま
+        assert_eq!(math("x^(a+b)"), "x^(a+b)");
+
+        let options = FormatOptions {
+            math_operator_spacing: false,
+            ..options()
+        };
+        assert_eq!(format_math("a+b=c", &options).ok().unwrap(), "a+b=c");
+    }
+
+    #[test]
+    fn syntax_error_unchanged() {
+        let text = "#f(a,\n= Title";
+        let errors = format_markup(text, &options()).err().unwrap();
+        assert!(!errors.is_empty());
+
+        let result = FormatResultSer::from_result(text, Err(errors));
+        assert_eq!(result.text, text);
+        assert!(!result.errors.is_empty());
+    }
+}
@@ -0,0 +1,226 @@
+use std::hash::{Hash, Hasher};
+
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+use serde::{Deserialize, Serialize};
+
+use typst::{
+    diag::SourceDiagnostic,
+    ecow::EcoVec,
+    layout::{Page, PagedDocument},
+    syntax::FileId,
+};
+
+use crate::budget::Interrupted;
+use crate::colors::ColorMapping;
+use crate::utils;
+
+pub const SHARED_GLYPH_PREFIX: &str = "typst-glyph-";
+
+pub enum InlineError {
+    NoPages,
+    Interrupted(Interrupted),
+    Diags(EcoVec<SourceDiagnostic>),
+}
+
+// ? 複数ページになったときの扱い
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Overflow {
+    // ? 先頭ページのみ (警告付き)
+    #[default]
+    First,
+    // ? 全ページを縦に並べて 1 つの SVG にする
+    Stitch,
+    // ? 全ページを返す (警告付き)
+    Pages,
+}
+
+impl Overflow {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "first" => Some(Self::First),
+            "stitch" => Some(Self::Stitch),
+            "pages" => Some(Self::Pages),
+            _ => None,
+        }
+    }
+}
+
+// ? pt．baseline は上端から，depth はベースラインから下端まで
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Metrics {
+    pub baseline: f64,
+    pub height: f64,
+    pub depth: f64,
+}
+
+// ? コンパイル済みのページ (SVG 化は呼び出し側で行う)．rest は Overflow::Pages の 2 ページ目以降
+pub struct InlineOutput {
+    pub page: Page,
+    pub rest: Vec<Page>,
+    // ? 結果キャッシュの検証に使う依存ファイルのパスとハッシュ
+    pub deps: Vec<(String, u64)>,
+    pub descent: f64,
+    pub metrics: Metrics,
+    pub warnings: EcoVec<SourceDiagnostic>,
+}
+
+pub fn render(page: &Page, descent: f64, colors: &ColorMapping) -> String {
+    colors.finish(typst_svg::svg(&colors.apply(page))).replacen(
+        "<svg class",
+        format!(
+            "<svg style=\"overflow: visible; vertical-align: {:.2}pt;\" class",
+            descent
+        )
+        .as_str(),
+        1,
+    )
+}
+
+// ? Overflow::Pages の 2 ページ目以降
+pub fn render_rest(output: &InlineOutput, colors: &ColorMapping) -> Vec<String> {
+    output
+        .rest
+        .iter()
+        .map(|page| render(page, 0.0, colors))
+        .collect()
+}
+
+pub fn default_prefix(kind: &str, id: &str) -> String {
+    let mut hasher = FxHasher::default();
+    kind.hash(&mut hasher);
+    id.hash(&mut hasher);
+    format!("t{:x}-", hasher.finish())
+}
+
+// ? 複数の SVG で共有するグリフ定義
+#[derive(Default)]
+pub struct GlyphSheet {
+    ids: FxHashSet<String>,
+    defs: String,
+}
+
+impl GlyphSheet {
+    // ? グリフ定義を取り出し，グリフは共有の id を，それ以外は prefix 付きの id を参照させる
+    pub fn share(&mut self, svg: &str, prefix: &str) -> String {
+        let (stripped, glyphs) = utils::extract_glyph_defs(svg);
+        let ids: FxHashSet<&str> = glyphs.iter().map(|(id, _)| id.as_str()).collect();
+
+        for (id, element) in &glyphs {
+            if self.ids.insert(id.clone()) {
+                self.defs
+                    .push_str(&utils::prefix_ids(element, |_| SHARED_GLYPH_PREFIX));
+            }
+        }
+
+        utils::prefix_ids(&stripped, |id| {
+            if ids.contains(id) {
+                SHARED_GLYPH_PREFIX
+            } else {
+                prefix
+            }
+        })
+    }
+
+    pub fn into_svg(self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" style=\"display: none;\"><defs>{}</defs></svg>",
+            self.defs
+        )
+    }
+}
+
+struct InlineEntry {
+    file_id: FileId,
+    document: Option<PagedDocument>,
+    used: u64,
+}
+
+// ? インライン数式ごとのソースと最後の文書を保持する LRU キャッシュ
+pub struct InlineCache {
+    capacity: usize,
+    tick: u64,
+    entries: FxHashMap<(String, String), InlineEntry>,
+}
+
+impl InlineCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            tick: 0,
+            entries: FxHashMap::default(),
+        }
+    }
+
+    // ? 追い出された FileId を返す
+    pub fn set_capacity(&mut self, capacity: usize) -> Vec<FileId> {
+        self.capacity = capacity.max(1);
+        self.shrink()
+    }
+
+    pub fn touch(&mut self, kind: &str, id: &str) -> Option<FileId> {
+        self.tick += 1;
+        let entry = self.entries.get_mut(&(kind.to_string(), id.to_string()))?;
+        entry.used = self.tick;
+
+        Some(entry.file_id)
+    }
+
+    pub fn insert(&mut self, kind: &str, id: &str, file_id: FileId) -> Vec<FileId> {
+        self.tick += 1;
+        let entry = InlineEntry {
+            file_id,
+            document: None,
+            used: self.tick,
+        };
+
+        let mut evicted = Vec::new();
+        if let Some(old) = self
+            .entries
+            .insert((kind.to_string(), id.to_string()), entry)
+            && old.file_id != file_id
+        {
+            evicted.push(old.file_id);
+        }
+        evicted.extend(self.shrink());
+
+        evicted
+    }
+
+    pub fn remove(&mut self, kind: &str, id: &str) -> Option<FileId> {
+        self.entries
+            .remove(&(kind.to_string(), id.to_string()))
+            .map(|entry| entry.file_id)
+    }
+
+    pub fn set_document(&mut self, kind: &str, id: &str, document: PagedDocument) {
+        if let Some(entry) = self.entries.get_mut(&(kind.to_string(), id.to_string())) {
+            entry.document = Some(document);
+        }
+    }
+
+    pub fn document(&self, kind: &str, id: &str) -> Option<&PagedDocument> {
+        self.entries
+            .get(&(kind.to_string(), id.to_string()))?
+            .document
+            .as_ref()
+    }
+
+    fn shrink(&mut self) -> Vec<FileId> {
+        let mut evicted = Vec::new();
+        while self.entries.len() > self.capacity {
+            let Some(key) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&key) {
+                evicted.push(entry.file_id);
+            }
+        }
+
+        evicted
+    }
+}
@@ -1,4 +1,5 @@
-use js_sys::{ArrayBuffer, Uint8Array};
+use js_sys::{ArrayBuffer, Int32Array, Uint8Array, Uint32Array};
+use rayon::prelude::*;
 use rustc_hash::FxHashMap;
 use serde_wasm_bindgen::to_value;
 use tylax::{
@@ -6,28 +7,50 @@ use tylax::{
     tikz::{convert_cetz_to_tikz, convert_tikz_to_cetz},
     typst_document_to_latex, typst_to_latex,
 };
+use typst_html::HtmlDocument;
+use typst_ide::Definition;
 use wasm_bindgen::prelude::*;
 
 use typst::{
     World,
-    diag::Warned,
-    foundations::Bytes,
+    diag::{SourceDiagnostic, Warned},
+    ecow::EcoVec,
+    foundations::{Bytes, Element, Selector, Smart, Value},
     layout::{Abs, PageRanges, PagedDocument, Point},
+    math::EquationElem,
     syntax::{
-        FileId, VirtualPath,
+        FileId, LinkedNode, Side, VirtualPath,
         package::{PackageSpec, PackageVersion},
     },
     text::FontInfo,
+    visualize::Paint,
 };
 
+mod budget;
+mod cache;
+mod colors;
+mod describe;
+mod formatter;
+mod inline;
+mod mathml;
+mod semantic;
 mod serde;
 mod utils;
 mod vfs;
 mod world;
 
-use crate::serde::{diagnostic, font, jump, options, package, pdfr, pngr, svg, svgp, svgr};
+use crate::cache::{CachedSvg, ResultCache};
+use crate::colors::ColorMapping;
+use crate::inline::{GlyphSheet, InlineCache, InlineError, InlineOutput, Metrics, Overflow};
+use crate::serde::{
+    completion, diagnostic, font, format, htmlr, interrupted, jump, label, mathml as mathml_ser,
+    options, outline, package, pdfr, pngr, reference, semantic as semantic_ser, signature, svg,
+    svg_batch, svgp, svgr, tooltip,
+};
 use crate::world::WasmWorld;
 
+const DEFAULT_INLINE_CAPACITY: usize = 256;
+
 #[wasm_bindgen]
 pub struct Typst {
     world: WasmWorld,
@@ -35,9 +58,14 @@ pub struct Typst {
 
     basepath: String,
 
-    last_kind: String,
-    last_id: String,
+    inline: InlineCache,
+    results: ResultCache,
+    colors: ColorMapping,
+    overflow: FxHashMap<String, Overflow>,
+    // ? svgp でコンパイルしたプレビューの文書 (インライン数式の文書は InlineCache が持つ)
     last_document: Option<PagedDocument>,
+
+    semantic_source: Option<semantic::SemanticSource>,
 }
 
 #[wasm_bindgen]
@@ -53,9 +81,13 @@ impl Typst {
 
             basepath,
 
-            last_kind: String::new(),
-            last_id: String::new(),
+            inline: InlineCache::new(DEFAULT_INLINE_CAPACITY),
+            results: ResultCache::new(fontsize),
+            colors: ColorMapping::base(),
+            overflow: FxHashMap::default(),
             last_document: None,
+
+            semantic_source: None,
         }
     }
 
@@ -63,6 +95,66 @@ impl Typst {
         self.offset = offset;
     }
 
+    pub fn set_inline_capacity(&mut self, capacity: usize) {
+        for file_id in self.inline.set_capacity(capacity) {
+            self.world.remove_file(file_id);
+        }
+    }
+
+    pub fn evict(&mut self, kind: &str, id: &str) {
+        if let Some(file_id) = self.inline.remove(kind, id) {
+            self.world.remove_file(file_id);
+        }
+    }
+
+    // ? インライン SVG の色の対応．結果キャッシュのキーにも反映する
+    pub fn set_color_mapping(&mut self, mapping: JsValue) -> Result<(), JsValue> {
+        let bytes = js_sys::JSON::stringify(&mapping)
+            .map(String::from)
+            .unwrap_or_default();
+        let mapping_ser: options::ColorMappingSer = serde_wasm_bindgen::from_value(mapping)
+            .map_err(|e| JsValue::from_str(&format!("failed to deserialize mapping: {}", e)))?;
+
+        self.colors = mapping_ser
+            .to_mapping()
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.results.record("colors".to_string(), bytes.as_bytes());
+
+        Ok(())
+    }
+
+    // ? kind ごとの複数ページの扱い ("first" | "stitch" | "pages")．結果キャッシュのキーにも反映する
+    pub fn set_overflow_mode(&mut self, kind: &str, mode: &str) -> Result<(), JsValue> {
+        let overflow = Overflow::parse(mode)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown overflow mode: {}", mode)))?;
+
+        self.overflow.insert(kind.to_string(), overflow);
+        self.results
+            .record(format!("overflow:{}", kind), mode.as_bytes());
+
+        Ok(())
+    }
+
+    // ? svg, svgp, pdfr, svgr, pngr の制限時間 (ms)．None で無制限
+    pub fn set_timeout(&mut self, timeout: Option<f64>) {
+        self.world.budget().set_timeout(timeout);
+    }
+
+    // ? [0] の値がコンパイル開始時から変わると中断する
+    pub fn set_cancel_token(&mut self, token: Option<Int32Array>) {
+        self.world.budget().set_token(token);
+    }
+
+    pub fn export_cache(&self) -> Uint8Array {
+        Uint8Array::from(self.results.export().as_slice())
+    }
+
+    pub fn import_cache(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        self.results
+            .import(bytes)
+            .map_err(|e| JsValue::from_str(&format!("failed to import cache: {}", e)))
+    }
+
     pub fn store(
         &mut self,
         fonts: Vec<ArrayBuffer>,
@@ -80,11 +172,13 @@ impl Typst {
             let mut vec = vec![0u8; u8arr.length() as usize];
             u8arr.copy_to(&mut vec);
 
+            self.results.record_font(&vec);
             self.world.add_font(Bytes::new(vec));
         }
 
         // ソース
         for (rpath, bytes) in sources_serde {
+            self.results.record(rpath.clone(), &bytes);
             if rpath.starts_with('@') {
                 // unwrap は TS 側で保証
                 let p = rpath.strip_prefix('@').unwrap();
@@ -117,15 +211,40 @@ impl Typst {
         }
 
         for (path, text) in files {
-            self.world.add_file_text(
-                VirtualPath::new(format!("{}/{}", self.basepath, path)),
-                text,
-            );
+            self.results.record(path.clone(), text.as_bytes());
+            let vpath = VirtualPath::new(format!("{}/{}", self.basepath, path));
+            self.world.mark_local(FileId::new(None, vpath.clone()));
+            self.world.add_file_text(vpath, text);
         }
 
         Ok(())
     }
 
+    // ? パス補完用のファイル一覧 (Vault 相対パス)
+    pub fn store_vault_files(&mut self, paths: JsValue) -> Result<(), JsValue> {
+        let paths: Vec<String> = serde_wasm_bindgen::from_value(paths)
+            .map_err(|e| JsValue::from_str(&format!("failed to deserialize paths: {}", e)))?;
+
+        let vpaths = paths
+            .iter()
+            .map(|path| VirtualPath::new(format!("{}/{}", self.basepath, path)))
+            .collect();
+        self.world.set_vault_files(vpaths);
+
+        Ok(())
+    }
+
+    // ? パッケージ補完用のインデックス (packages.typst.org/preview/index.json 形式)
+    pub fn store_package_index(&mut self, index: JsValue) -> Result<(), JsValue> {
+        let entries: Vec<package::PackageIndexEntrySer> = serde_wasm_bindgen::from_value(index)
+            .map_err(|e| JsValue::from_str(&format!("failed to deserialize index: {}", e)))?;
+
+        let index = entries.iter().filter_map(|e| e.to_spec()).collect();
+        self.world.set_package_index(index);
+
+        Ok(())
+    }
+
     pub fn list_packages(&self) -> JsValue {
         let packages = self.world.list_packages();
         let packages_ser: Vec<package::PackageSpecSer> = packages.iter().map(Into::into).collect();
@@ -155,80 +274,248 @@ impl Typst {
 
 #[wasm_bindgen]
 impl Typst {
-    fn update_source(&mut self, vpath: VirtualPath, code: &str) {
+    fn set_source(&mut self, vpath: VirtualPath, code: &str) {
         let file_id = FileId::new(None, vpath.clone());
-        let result = self.world.source(file_id);
 
-        match result {
+        match self.world.source(file_id) {
             Ok(_source) => {
                 self.world.set_main(file_id);
                 self.world.replace(code);
             }
             Err(_e) => {
-                self.world.add_file_text(vpath.clone(), code.into());
+                self.world.add_file_text(vpath, code.into());
                 self.world.set_main(file_id);
             }
         }
     }
 
-    // Markdown 用
-    pub fn svg(
+    // ? ノートのソースを設定する．code が None のときは読み込み済みのソースを使う (存在しなければエラー)
+    fn update_source(&mut self, vpath: VirtualPath, code: Option<&str>) -> Result<(), JsValue> {
+        let file_id = FileId::new(None, vpath.clone());
+        self.world.mark_local(file_id);
+
+        let Some(code) = code else {
+            self.world.source(file_id).map_err(|e| {
+                JsValue::from_str(&format!(
+                    "failed to read {}: {}",
+                    vpath.as_rooted_path().display(),
+                    e
+                ))
+            })?;
+            self.world.set_main(file_id);
+            return Ok(());
+        };
+
+        self.set_source(vpath, code);
+        Ok(())
+    }
+
+    fn main_text(&self) -> String {
+        self.world
+            .source(self.world.main())
+            .map(|source| source.text().to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn edit(&mut self, path: &str, from: usize, to: usize, text: &str) -> Result<(), JsValue> {
+        let vpath = VirtualPath::new(format!("{}/{}", self.basepath, path));
+
+        self.world
+            .edit(vpath, from, to, text)
+            .ok_or_else(|| JsValue::from_str("failed to edit source"))
+    }
+
+    // ? インライン数式のソースを main にする (InlineCache を通し，追い出されたファイルは削除する)
+    fn set_inline_source(&mut self, code: &str, ndir: &str, kind: &str, id: &str) -> FileId {
+        let vpath = VirtualPath::new(format!("{}{}{}_{}.typ", self.basepath, ndir, kind, id));
+        let file_id = FileId::new(None, vpath.clone());
+        match self.inline.touch(kind, id) {
+            Some(cached) if cached == file_id => {
+                self.world.set_main(file_id);
+                self.world.replace(code);
+            }
+            _ => {
+                self.set_source(vpath, code);
+                for evicted in self.inline.insert(kind, id, file_id) {
+                    self.world.remove_file(evicted);
+                }
+            }
+        }
+
+        file_id
+    }
+
+    // ? インライン数式をコンパイルし，kind の Overflow に従ってページを取り出す
+    fn compile_inline(
         &mut self,
         code: &str,
-        ndir: &str, // ndir starts and ends with "/"
+        ndir: &str,
         kind: &str,
         id: &str,
-    ) -> Result<JsValue, JsValue> {
-        if self.last_kind == kind && self.last_id == id {
-            self.world.replace(code);
-        } else {
-            self.last_kind = kind.to_string();
-            self.last_id = id.to_string();
+    ) -> Result<InlineOutput, InlineError> {
+        let file_id = self.set_inline_source(code, ndir, kind, id);
+        let Warned {
+            output,
+            mut warnings,
+        } = {
+            self.world.clear_accessed();
+            typst::compile::<PagedDocument>(&mut self.world)
+        };
+        let deps = self.world.dependencies();
 
-            self.update_source(
-                VirtualPath::new(format!("{}{}{}_{}.typ", self.basepath, ndir, kind, id)),
-                code,
-            );
+        if let Some(reason) = self.world.budget().interrupted() {
+            return Err(InlineError::Interrupted(reason));
         }
-        let Warned { output, warnings } = typst::compile::<PagedDocument>(&mut self.world);
 
-        match output {
-            Ok(mut document) => {
-                if document.pages.is_empty() {
-                    return Err(JsValue::from_str("document has no pages"));
-                }
+        let mut document = output.map_err(InlineError::Diags)?;
+        if document.pages.is_empty() {
+            return Err(InlineError::NoPages);
+        }
+        for page in document.pages.iter_mut() {
+            if None == page.fill_or_transparent() {
+                page.fill = typst::foundations::Smart::Custom(None);
+            };
+        }
 
-                let page = &mut document.pages[0];
-                if None == page.fill_or_transparent() {
-                    page.fill = typst::foundations::Smart::Custom(None);
-                };
-                let frame = &page.frame;
-                let descent = if kind == "inline" {
-                    (match utils::find_baseline(frame, Abs::zero()) {
-                        Some(b) => (b - frame.height()).to_pt(),
-                        None => -frame.height().to_pt(),
-                    }) + self.offset
+        let count = document.pages.len();
+        let overflow = self.overflow.get(kind).copied().unwrap_or_default();
+        let (page, rest) = match overflow {
+            _ if count == 1 => (document.pages[0].clone(), Vec::new()),
+            Overflow::Stitch => {
+                let page = utils::stitch_pages(&document.pages).ok_or(InlineError::NoPages)?;
+                // ? クリック位置とページを対応させるため，文書も 1 ページにしておく
+                document.pages = vec![page.clone()];
+                (page, Vec::new())
+            }
+            Overflow::First | Overflow::Pages => {
+                let message = if overflow == Overflow::First {
+                    format!("formula spans {} pages, only the first is rendered", count)
                 } else {
-                    0.0
+                    format!("formula spans {} pages", count)
                 };
+                if let Ok(source) = self.world.source(file_id) {
+                    warnings.push(SourceDiagnostic::warning(source.root().span(), message));
+                }
+                let rest = match overflow {
+                    Overflow::Pages => document.pages[1..].to_vec(),
+                    _ => Vec::new(),
+                };
+                (document.pages[0].clone(), rest)
+            }
+        };
 
-                let svg = typst_svg::svg(&page)
-                    .replace("#000000", "var(--typst-base-color)")
-                    .replacen(
-                        "<svg class",
-                        format!(
-                            "<svg style=\"overflow: visible; vertical-align: {:.2}pt;\" class",
-                            descent
-                        )
-                        .as_str(),
-                        1,
-                    );
+        let frame = &page.frame;
+        let height = frame.height().to_pt();
+        let baseline = utils::find_baseline(frame, Abs::zero())
+            .map(|b| b.to_pt())
+            .unwrap_or(height);
+        let metrics = Metrics {
+            baseline,
+            height,
+            depth: height - baseline,
+        };
+        let descent = if kind == "inline" {
+            -metrics.depth + self.offset
+        } else {
+            0.0
+        };
 
-                self.last_document = Some(document);
+        // ? プレビューの文書 (last_document) は置き換えない
+        self.inline.set_document(kind, id, document);
+
+        Ok(InlineOutput {
+            page,
+            rest,
+            deps,
+            descent,
+            metrics,
+            warnings,
+        })
+    }
+
+    // ? 結果キャッシュにあれば SVG を返す
+    fn cached_inline(&mut self, key: u64, code: &str, kind: &str, id: &str) -> Option<CachedSvg> {
+        let world = &self.world;
+        let cached = self
+            .results
+            .get(key, |path| world.dependency_hash(path))?
+            .clone();
+
+        // ? ジャンプ用の文書が古いソースのものであれば破棄する
+        if let Some(file_id) = self.inline.touch(kind, id)
+            && !self
+                .world
+                .source(file_id)
+                .is_ok_and(|source| source.text() == code)
+        {
+            self.evict(kind, id);
+        }
+
+        Some(cached)
+    }
+
+    // Markdown 用
+    pub fn svg(
+        &mut self,
+        code: &str,
+        ndir: &str, // ndir starts and ends with "/"
+        kind: &str,
+        id: &str,
+        alt: JsValue,
+        id_prefix: Option<String>,
+    ) -> Result<JsValue, JsValue> {
+        let key = self.results.key(code, ndir, kind, self.offset);
+        if let Some(cached) = self.cached_inline(key, code, kind, id) {
+            let alt = parse_alt(alt)?;
+            let prefix = id_prefix.unwrap_or_else(|| inline::default_prefix(kind, id));
+            let svg = finish_inline(cached.svg, alt.as_ref(), code, kind, &prefix, None);
+            let pages = finish_pages(cached.pages, alt.as_ref(), code, kind, &prefix, None);
+            return svg::svg(svg, pages, cached.metrics, EcoVec::new(), &self.world);
+        }
+
+        self.svg_uncached(code, ndir, kind, id, alt, id_prefix)
+    }
 
-                svg::svg(svg, warnings, &self.world)
+    // ? 結果キャッシュを使わずにコンパイルする (ジャンプ用の文書を作るため)
+    pub fn svg_uncached(
+        &mut self,
+        code: &str,
+        ndir: &str,
+        kind: &str,
+        id: &str,
+        alt: JsValue,
+        id_prefix: Option<String>,
+    ) -> Result<JsValue, JsValue> {
+        let alt = parse_alt(alt)?;
+        let prefix = id_prefix.unwrap_or_else(|| inline::default_prefix(kind, id));
+        let key = self.results.key(code, ndir, kind, self.offset);
+
+        self.world.budget().start();
+        let result = self.compile_inline(code, ndir, kind, id);
+        self.world.budget().finish();
+
+        match result {
+            Ok(output) => {
+                let svg = inline::render(&output.page, output.descent, &self.colors);
+                let pages = inline::render_rest(&output, &self.colors);
+                if output.warnings.is_empty() {
+                    self.results.insert(
+                        key,
+                        CachedSvg {
+                            svg: svg.clone(),
+                            metrics: output.metrics,
+                            pages: pages.clone(),
+                        },
+                        output.deps.clone(),
+                    );
+                }
+                let svg = finish_inline(svg, alt.as_ref(), code, kind, &prefix, None);
+                let pages = finish_pages(pages, alt.as_ref(), code, kind, &prefix, None);
+                svg::svg(svg, pages, output.metrics, output.warnings, &self.world)
             }
-            Err(errs) => {
+            Err(InlineError::NoPages) => Err(JsValue::from_str("document has no pages")),
+            Err(InlineError::Interrupted(reason)) => Err(interrupted::interrupted(reason)),
+            Err(InlineError::Diags(errs)) => {
                 let diags: Vec<diagnostic::SourceDiagnosticSer> = errs
                     .iter()
                     .map(|d| diagnostic::SourceDiagnosticSer::from_diag(d, &self.world))
@@ -238,21 +525,161 @@ impl Typst {
         }
     }
 
+    // ? コンパイルは順に行い (comemo のキャッシュを共有)，SVG 化は並列に行う
+    pub fn svg_batch(&mut self, items: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+        let items: Vec<svg_batch::SvgBatchItemSer> = serde_wasm_bindgen::from_value(items)?;
+        let options: Option<svg_batch::SvgBatchOptionsSer> =
+            serde_wasm_bindgen::from_value(options)?;
+        let mut sheet = options
+            .unwrap_or_default()
+            .shared_defs
+            .then(GlyphSheet::default);
+
+        let keys: Vec<u64> = items
+            .iter()
+            .map(|item| {
+                self.results
+                    .key(&item.code, &item.ndir, &item.kind, self.offset)
+            })
+            .collect();
+        let cached: Vec<Option<CachedSvg>> = items
+            .iter()
+            .zip(&keys)
+            .map(|(item, key)| self.cached_inline(*key, &item.code, &item.kind, &item.id))
+            .collect();
+
+        self.world.budget().start();
+        let outputs: Vec<Option<svg_batch::BatchOutput>> = items
+            .iter()
+            .zip(&cached)
+            .map(|(item, cached)| match cached {
+                Some(_) => None,
+                None => {
+                    let output = self.compile_inline(&item.code, &item.ndir, &item.kind, &item.id);
+                    self.world.budget().pause();
+                    let output = svg_batch::BatchOutput::new(output, &self.world);
+                    self.world.budget().resume();
+                    Some(output)
+                }
+            })
+            .collect();
+        self.world.budget().finish();
+        let rendered: Vec<Option<(String, Vec<String>)>> = outputs
+            .par_iter()
+            .map(|output| match output {
+                Some(svg_batch::BatchOutput {
+                    result: Ok(output), ..
+                }) => Some((
+                    inline::render(&output.page, output.descent, &self.colors),
+                    inline::render_rest(output, &self.colors),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        let svgs: Vec<Option<CachedSvg>> = cached
+            .into_iter()
+            .zip(rendered)
+            .zip(&outputs)
+            .zip(keys)
+            .zip(&items)
+            .map(|((((cached, rendered), output), key), item)| {
+                let rendered = match (rendered, output) {
+                    (
+                        Some((svg, pages)),
+                        Some(svg_batch::BatchOutput {
+                            result: Ok(output), ..
+                        }),
+                    ) => {
+                        let rendered = CachedSvg {
+                            svg,
+                            metrics: output.metrics,
+                            pages,
+                        };
+                        if output.warnings.is_empty() {
+                            self.results
+                                .insert(key, rendered.clone(), output.deps.clone());
+                        }
+                        Some(rendered)
+                    }
+                    _ => None,
+                };
+                cached.or(rendered).map(|cached| {
+                    let prefix = item
+                        .id_prefix
+                        .clone()
+                        .unwrap_or_else(|| inline::default_prefix(&item.kind, &item.id));
+                    CachedSvg {
+                        svg: finish_inline(
+                            cached.svg,
+                            item.alt.as_ref(),
+                            &item.code,
+                            &item.kind,
+                            &prefix,
+                            sheet.as_mut(),
+                        ),
+                        metrics: cached.metrics,
+                        pages: finish_pages(
+                            cached.pages,
+                            item.alt.as_ref(),
+                            &item.code,
+                            &item.kind,
+                            &prefix,
+                            sheet.as_mut(),
+                        ),
+                    }
+                })
+            })
+            .collect();
+
+        let defs = sheet.map(GlyphSheet::into_svg);
+        svg_batch::svg_batch(outputs, svgs, defs)
+    }
+
     // プレビュー用
-    pub fn svgp(&mut self, ndir: &str, filename: &str, code: &str) -> Result<JsValue, JsValue> {
+    pub fn svgp(
+        &mut self,
+        ndir: &str,
+        filename: &str,
+        code: Option<String>,
+        alt: JsValue,
+        colors: JsValue,
+        id_prefix: Option<String>,
+    ) -> Result<JsValue, JsValue> {
+        let prefix = id_prefix.unwrap_or_else(|| inline::default_prefix(ndir, filename));
+        let alt = parse_alt(alt)?;
+        let colors: Option<options::ColorMappingSer> = serde_wasm_bindgen::from_value(colors)
+            .map_err(|e| JsValue::from_str(&format!("failed to deserialize colors: {}", e)))?;
+        let colors = colors
+            .map(|colors| colors.to_mapping())
+            .transpose()
+            .map_err(|e| JsValue::from_str(&e))?;
         self.update_source(
             VirtualPath::new(format!("{}{}{}", self.basepath, ndir, filename)),
-            code,
-        );
+            code.as_deref(),
+        )?;
         self.world.update_now();
+        self.world.budget().start();
         let Warned { output, warnings } = typst::compile::<PagedDocument>(&mut self.world);
+        if let Some(reason) = self.world.budget().finish() {
+            return Err(interrupted::interrupted(reason));
+        }
 
         match output {
             Ok(mut document) => {
+                let description = alt.map(|alt| alt.describe(&self.main_text(), false));
                 let mut svgs = Vec::new();
-                for page in &mut document.pages {
-                    let svg = typst_svg::svg(page);
-                    svgs.push(svg);
+                for (i, page) in document.pages.iter_mut().enumerate() {
+                    let svg = match &colors {
+                        Some(colors) => colors.finish(typst_svg::svg(&colors.apply(page))),
+                        None => typst_svg::svg(page),
+                    };
+                    let page_prefix = format!("{}p{}-", prefix, i);
+                    let svg = utils::prefix_ids(&svg, |_| page_prefix.as_str());
+                    svgs.push(match &description {
+                        Some(description) => describe::embed(&svg, description),
+                        None => svg,
+                    });
                 }
                 self.last_document = Some(document);
                 svgp::svgp(svgs, warnings, &self.world)
@@ -270,8 +697,8 @@ impl Typst {
 
 #[wasm_bindgen]
 impl Typst {
-    pub fn jump_from_click(&self, x: f64, y: f64) -> JsValue {
-        match &self.last_document {
+    pub fn jump_from_click(&self, kind: &str, id: &str, x: f64, y: f64) -> JsValue {
+        match self.inline.document(kind, id) {
             Some(document) => {
                 let frame = &document.pages[0].frame;
                 let point = Point::new(Abs::pt(x), Abs::pt(y));
@@ -331,11 +758,208 @@ impl Typst {
 
 #[wasm_bindgen]
 impl Typst {
-    pub fn pdfr(
+    // ? cursor は UTF-16 オフセット
+    pub fn autocomplete(
         &mut self,
         ndir: &str,
         filename: &str,
         code: &str,
+        cursor: usize,
+        explicit: bool,
+    ) -> JsValue {
+        let vpath = VirtualPath::new(format!("{}{}{}", self.basepath, ndir, filename));
+        self.world.mark_local(FileId::new(None, vpath.clone()));
+        self.set_source(vpath, code);
+
+        self.complete(self.last_document.as_ref(), cursor, explicit)
+    }
+
+    // ? インライン数式は InlineCache を通し，その数式の文書を使う
+    pub fn autocomplete_inline(
+        &mut self,
+        code: &str,
+        ndir: &str,
+        kind: &str,
+        id: &str,
+        cursor: usize,
+        explicit: bool,
+    ) -> JsValue {
+        self.set_inline_source(code, ndir, kind, id);
+
+        self.complete(self.inline.document(kind, id), cursor, explicit)
+    }
+
+    fn complete(&self, document: Option<&PagedDocument>, cursor: usize, explicit: bool) -> JsValue {
+        let Ok(source) = self.world.source(self.world.main()) else {
+            return JsValue::NULL;
+        };
+        let Some(cursor) = source.lines().utf16_to_byte(cursor) else {
+            return JsValue::NULL;
+        };
+
+        let result = typst_ide::autocomplete(&self.world, document, &source, cursor, explicit);
+        match result {
+            Some((from, completions)) => {
+                let completions_ser =
+                    completion::CompletionsSer::new(from, cursor, &completions, &source);
+                to_value(&completions_ser).unwrap_or(JsValue::NULL)
+            }
+            None => JsValue::NULL,
+        }
+    }
+
+    // ? side は "before" | "after"
+    pub fn tooltip(&self, cursor: usize, side: &str) -> JsValue {
+        let Ok(source) = self.world.source(self.world.main()) else {
+            return JsValue::NULL;
+        };
+        let Some(cursor) = source.lines().utf16_to_byte(cursor) else {
+            return JsValue::NULL;
+        };
+        let side = match side {
+            "before" => Side::Before,
+            _ => Side::After,
+        };
+
+        let result = typst_ide::tooltip(
+            &self.world,
+            self.last_document.as_ref(),
+            &source,
+            cursor,
+            side,
+        );
+        match result {
+            Some(tooltip) => {
+                let tooltip_ser: tooltip::TooltipSer = (&tooltip).into();
+                to_value(&tooltip_ser).unwrap_or(JsValue::NULL)
+            }
+            None => JsValue::NULL,
+        }
+    }
+
+    pub fn definition(&self, cursor: usize) -> JsValue {
+        let Ok(source) = self.world.source(self.world.main()) else {
+            return JsValue::NULL;
+        };
+        let Some(cursor) = source.lines().utf16_to_byte(cursor) else {
+            return JsValue::NULL;
+        };
+
+        let result = typst_ide::definition(
+            &self.world,
+            self.last_document.as_ref(),
+            &source,
+            cursor,
+            Side::After,
+        );
+        match result {
+            // ? 標準ライブラリの定義はソースを持たない
+            Some(Definition::Span(span)) => match jump::JumpSer::from_span(span, &self.world) {
+                Some(jump_ser) => to_value(&jump_ser).unwrap_or(JsValue::NULL),
+                None => JsValue::NULL,
+            },
+            Some(Definition::Std(_)) | None => JsValue::NULL,
+        }
+    }
+
+    pub fn document_symbols(&self) -> JsValue {
+        let Ok(source) = self.world.source(self.world.main()) else {
+            return JsValue::NULL;
+        };
+
+        let symbols_ser =
+            outline::document_symbols(&source, self.last_document.as_ref(), &self.world);
+        to_value(&symbols_ser).unwrap_or(JsValue::NULL)
+    }
+
+    pub fn list_labels(&self) -> JsValue {
+        let Some(document) = &self.last_document else {
+            return JsValue::NULL;
+        };
+        let Ok(source) = self.world.source(self.world.main()) else {
+            return JsValue::NULL;
+        };
+
+        let labels_ser = label::LabelsSer::new(document, &source);
+        to_value(&labels_ser).unwrap_or(JsValue::NULL)
+    }
+
+    pub fn references(&self, cursor: usize) -> JsValue {
+        let Ok(source) = self.world.source(self.world.main()) else {
+            return JsValue::NULL;
+        };
+        let Some(cursor) = source.lines().utf16_to_byte(cursor) else {
+            return JsValue::NULL;
+        };
+        let Some(target) = reference::find_target(&self.world, &source, cursor) else {
+            return JsValue::NULL;
+        };
+
+        let references_ser: Vec<reference::ReferenceSer> =
+            reference::find_occurrences(&self.world, &target)
+                .iter()
+                .filter_map(|o| reference::ReferenceSer::from_occurrence(o, &self.world))
+                .collect();
+        to_value(&references_ser).unwrap_or(JsValue::NULL)
+    }
+
+    pub fn rename(&self, cursor: usize, new_name: &str) -> Result<JsValue, JsValue> {
+        let target = self
+            .world
+            .source(self.world.main())
+            .ok()
+            .and_then(|source| {
+                let cursor = source.lines().utf16_to_byte(cursor)?;
+                reference::find_target(&self.world, &source, cursor)
+            });
+        let Some(target) = target else {
+            return Err(to_value(&rename::RenameErrorSer::NoTarget).unwrap_or(JsValue::NULL));
+        };
+
+        match rename::rename(&self.world, &target, new_name) {
+            Ok(edits) => Ok(to_value(&edits)?),
+            Err(err) => Err(to_value(&err).unwrap_or(JsValue::NULL)),
+        }
+    }
+
+    pub fn signature_help(&self, cursor: usize) -> JsValue {
+        let Ok(source) = self.world.source(self.world.main()) else {
+            return JsValue::NULL;
+        };
+        let Some(cursor) = source.lines().utf16_to_byte(cursor) else {
+            return JsValue::NULL;
+        };
+
+        let root = LinkedNode::new(source.root());
+        let Some(call) = utils::find_call(&root, cursor) else {
+            return JsValue::NULL;
+        };
+
+        let values = typst_ide::analyze_expr(&self.world, &call.callee);
+        let Some(func) = values.iter().find_map(|(value, _)| match value {
+            Value::Func(func) => Some(func.clone()),
+            _ => None,
+        }) else {
+            return JsValue::NULL;
+        };
+
+        match signature::SignatureSer::from_func(&func, &self.world) {
+            Some(mut signature_ser) => {
+                signature_ser.activate(call.positional, call.named.as_deref());
+                to_value(&signature_ser).unwrap_or(JsValue::NULL)
+            }
+            None => JsValue::NULL,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Typst {
+    pub fn pdfr(
+        &mut self,
+        ndir: &str,
+        filename: &str,
+        code: Option<String>,
         options: JsValue,
     ) -> Result<JsValue, JsValue> {
         let options_ser: options::PdfOptionsSer = serde_wasm_bindgen::from_value(options)
@@ -344,9 +968,13 @@ impl Typst {
         self.world.update_now();
         self.update_source(
             VirtualPath::new(format!("{}{}{}", self.basepath, ndir, filename)),
-            code,
-        );
+            code.as_deref(),
+        )?;
+        self.world.budget().start();
         let Warned { output, warnings } = typst::compile::<PagedDocument>(&mut self.world);
+        if let Some(reason) = self.world.budget().finish() {
+            return Err(interrupted::interrupted(reason));
+        }
 
         match output {
             Ok(mut document) => {
@@ -416,11 +1044,70 @@ impl Typst {
         }
     }
 
+    pub fn htmlr(
+        &mut self,
+        ndir: &str,
+        filename: &str,
+        code: Option<String>,
+        options: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let options_ser: options::HtmlOptionsSer = serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("failed to deserialize options: {}", e)))?;
+
+        self.world.update_now();
+        let vpath = VirtualPath::new(format!("{}{}{}", self.basepath, ndir, filename));
+        let note = FileId::new(None, vpath.clone());
+        self.update_source(vpath, code.as_deref())?;
+        let wrapper = options_ser.math_svg.then(|| {
+            // ? 数式を SVG にするラッパーから本体を読み込む (本体の Span はそのまま)
+            let vpath =
+                VirtualPath::new(format!("{}{}.{}.html.typ", self.basepath, ndir, filename));
+            self.set_source(
+                vpath.clone(),
+                &format!("#show math.equation: html.frame\n#include {:?}\n", filename),
+            );
+            FileId::new(None, vpath)
+        });
+        let Warned { output, warnings } = typst::compile::<HtmlDocument>(&mut self.world);
+
+        let result = match output {
+            Ok(mut document) => {
+                document.info.title.get_or_insert_with(|| filename.into());
+
+                match typst_html::html(&document) {
+                    Ok(html) => htmlr::htmlr(html, warnings, &self.world),
+                    Err(errs) => {
+                        let diags: Vec<diagnostic::SourceDiagnosticSer> = errs
+                            .iter()
+                            .map(|d| diagnostic::SourceDiagnosticSer::from_diag(d, &self.world))
+                            .collect();
+                        Err(to_value(&diags).unwrap_or(JsValue::NULL))
+                    }
+                }
+            }
+            Err(errs) => {
+                let diags: Vec<diagnostic::SourceDiagnosticSer> = errs
+                    .iter()
+                    .map(|d| diagnostic::SourceDiagnosticSer::from_diag(d, &self.world))
+                    .collect();
+                Err(to_value(&diags).unwrap_or(JsValue::NULL))
+            }
+        };
+
+        // ? カーソル位置を使う API やパス補完がラッパーを対象にしないよう，main をノートに戻して削除する
+        if let Some(wrapper) = wrapper {
+            self.world.set_main(note);
+            self.world.remove_file(wrapper);
+        }
+
+        result
+    }
+
     pub fn svgr(
         &mut self,
         ndir: &str,
         filename: &str,
-        code: &str,
+        code: Option<String>,
         options: JsValue,
     ) -> Result<JsValue, JsValue> {
         let options_ser: options::SvgOptionsSer = serde_wasm_bindgen::from_value(options)
@@ -429,9 +1116,13 @@ impl Typst {
         self.world.update_now();
         self.update_source(
             VirtualPath::new(format!("{}{}{}", self.basepath, ndir, filename)),
-            code,
-        );
+            code.as_deref(),
+        )?;
+        self.world.budget().start();
         let Warned { output, warnings } = typst::compile::<PagedDocument>(&mut self.world);
+        if let Some(reason) = self.world.budget().finish() {
+            return Err(interrupted::interrupted(reason));
+        }
 
         match output {
             Ok(document) => {
@@ -439,6 +1130,14 @@ impl Typst {
                     .page_ranges
                     .as_ref()
                     .and_then(|s| utils::parse_page_ranges(s));
+                let description = options_ser
+                    .alt
+                    .as_ref()
+                    .map(|alt| alt.describe(&self.main_text(), false));
+                let prefix = options_ser
+                    .id_prefix
+                    .clone()
+                    .unwrap_or_else(|| inline::default_prefix(ndir, filename));
                 let mut svgs = Vec::new();
                 for (i, page) in document.pages.iter().enumerate() {
                     if let Some(ranges) = &page_ranges {
@@ -446,8 +1145,17 @@ impl Typst {
                             continue;
                         }
                     }
-                    let svg = typst_svg::svg(page);
-                    svgs.push(svg);
+                    let svg = if options_ser.crop {
+                        typst_svg::svg(&utils::crop_page(page, Abs::pt(options_ser.crop_padding)))
+                    } else {
+                        typst_svg::svg(page)
+                    };
+                    let page_prefix = format!("{}p{}-", prefix, i);
+                    let svg = utils::prefix_ids(&svg, |_| page_prefix.as_str());
+                    svgs.push(match &description {
+                        Some(description) => describe::embed(&svg, description),
+                        None => svg,
+                    });
                 }
                 svgr::svgr(svgs, warnings, &self.world)
             }
@@ -465,7 +1173,7 @@ impl Typst {
         &mut self,
         ndir: &str,
         filename: &str,
-        code: &str,
+        code: Option<String>,
         options: JsValue,
     ) -> Result<JsValue, JsValue> {
         let options_ser: options::PngOptionsSer = serde_wasm_bindgen::from_value(options)
@@ -474,9 +1182,13 @@ impl Typst {
         self.world.update_now();
         self.update_source(
             VirtualPath::new(format!("{}{}{}", self.basepath, ndir, filename)),
-            code,
-        );
+            code.as_deref(),
+        )?;
+        self.world.budget().start();
         let Warned { output, warnings } = typst::compile::<PagedDocument>(&mut self.world);
+        if let Some(reason) = self.world.budget().finish() {
+            return Err(interrupted::interrupted(reason));
+        }
 
         match output {
             Ok(document) => {
@@ -485,6 +1197,19 @@ impl Typst {
                     .page_ranges
                     .as_ref()
                     .and_then(|s| utils::parse_page_ranges(s));
+                let background = options_ser
+                    .background
+                    .as_deref()
+                    .map(colors::parse_background)
+                    .transpose()
+                    .map_err(|e| JsValue::from_str(&e))?;
+                let base_color = options_ser
+                    .base_color
+                    .as_deref()
+                    .map(colors::parse_color)
+                    .transpose()
+                    .map_err(|e| JsValue::from_str(&e))?;
+
                 let mut images = Vec::new();
                 for (i, page) in document.pages.iter().enumerate() {
                     if let Some(ranges) = &page_ranges {
@@ -492,7 +1217,40 @@ impl Typst {
                             continue;
                         }
                     }
-                    let pixmap = typst_render::render(page, ppi / 72.0);
+                    let mut page = if options_ser.crop {
+                        utils::crop_page(page, Abs::pt(options_ser.crop_padding))
+                    } else {
+                        page.clone()
+                    };
+                    if let Some(base_color) = base_color {
+                        page.frame = colors::map_frame(&page.frame, &|color| {
+                            if colors::is_black(color) {
+                                base_color
+                            } else {
+                                color
+                            }
+                        });
+                    }
+                    match &background {
+                        Some(colors::Background::Transparent) => {
+                            page.fill = Smart::Custom(None);
+                        }
+                        Some(colors::Background::Color(color)) => {
+                            page.fill = Smart::Custom(Some(Paint::Solid(*color)));
+                        }
+                        Some(colors::Background::Page) | None => {}
+                    }
+
+                    // ? 最大サイズに収まるように倍率を下げる
+                    let size = page.frame.size();
+                    let mut scale = ppi / 72.0;
+                    if let Some(max_width) = options_ser.max_width {
+                        scale = scale.min((max_width / size.x.to_pt()) as f32);
+                    }
+                    if let Some(max_height) = options_ser.max_height {
+                        scale = scale.min((max_height / size.y.to_pt()) as f32);
+                    }
+                    let pixmap = typst_render::render(&page, scale);
                     let png = pixmap
                         .encode_png()
                         .map_err(|e| JsValue::from_str(&e.to_string()))?;
@@ -511,6 +1269,102 @@ impl Typst {
     }
 }
 
+#[wasm_bindgen]
+impl Typst {
+    pub fn mathml(&mut self, code: &str, display: bool) -> Result<JsValue, JsValue> {
+        let (open, close) = if display { ("$ ", " $") } else { ("$", "$") };
+        self.set_source(
+            VirtualPath::new(format!("{}/.mathml.typ", self.basepath)),
+            &format!("{}{}{}", open, code, close),
+        );
+        let Warned { output, warnings } = typst::compile::<PagedDocument>(&mut self.world);
+
+        match output {
+            Ok(document) => {
+                let selector = Selector::Elem(Element::of::<EquationElem>(), None);
+                let equations = document.introspector.query(&selector);
+                let Some(body) = equations.first().and_then(|eq| eq.get_by_name("body").ok())
+                else {
+                    return Err(JsValue::from_str("no equation found"));
+                };
+                let Value::Content(body) = body else {
+                    return Err(JsValue::from_str("no equation found"));
+                };
+
+                let (mathml, mut diags) = mathml::convert(&body, display);
+                diags.extend(warnings);
+                mathml_ser::mathml(mathml, diags, open.len(), &self.world)
+            }
+            Err(errs) => {
+                let diags: Vec<diagnostic::SourceDiagnosticSer> = errs
+                    .iter()
+                    .map(|d| {
+                        let mut diag = diagnostic::SourceDiagnosticSer::from_diag(d, &self.world);
+                        diag.from = diag.from.saturating_sub(open.len());
+                        diag.to = diag.to.saturating_sub(open.len());
+                        diag
+                    })
+                    .collect();
+                Err(to_value(&diags).unwrap_or(JsValue::NULL))
+            }
+        }
+    }
+
+    pub fn format(&self, code: &str, options: JsValue) -> Result<JsValue, JsValue> {
+        let options_ser: options::FormatOptionsSer = serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("failed to deserialize options: {}", e)))?;
+
+        let options = formatter::FormatOptions {
+            indent_width: options_ser.indent_width,
+            max_width: options_ser.max_width,
+            math_operator_spacing: options_ser.math_operator_spacing,
+        };
+        let result = if options_ser.math {
+            formatter::format_math(code, &options)
+        } else {
+            formatter::format_markup(code, &options)
+        };
+
+        Ok(to_value(&format::FormatResultSer::from_result(
+            code, result,
+        ))?)
+    }
+}
+
+#[wasm_bindgen]
+impl Typst {
+    pub fn semantic_token_legend(&self) -> JsValue {
+        to_value(&semantic_ser::legend()).unwrap_or(JsValue::NULL)
+    }
+
+    // ? mode は "markup" | "code" | "math"
+    pub fn semantic_tokens(&mut self, code: &str, mode: &str) -> Uint32Array {
+        let source = semantic::SemanticSource::new(code, semantic::Mode::from_name(mode));
+
+        let data = source.tokens();
+        self.semantic_source = Some(source);
+
+        Uint32Array::from(data.as_slice())
+    }
+
+    // ? 前回の semantic_tokens のソースに差分を適用する
+    pub fn semantic_tokens_edit(
+        &mut self,
+        from: usize,
+        to: usize,
+        text: &str,
+    ) -> Result<Uint32Array, JsValue> {
+        let Some(source) = self.semantic_source.as_mut() else {
+            return Err(JsValue::from_str("no previous source"));
+        };
+        source
+            .edit(from, to, text)
+            .ok_or_else(|| JsValue::from_str("edit range out of bounds"))?;
+
+        Ok(Uint32Array::from(source.tokens().as_slice()))
+    }
+}
+
 #[wasm_bindgen]
 impl Typst {
     pub fn latex_to_typst(&self, code: &str) -> String {
@@ -534,3 +1388,49 @@ impl Typst {
         convert_cetz_to_tikz(code)
     }
 }
+
+fn parse_alt(alt: JsValue) -> Result<Option<options::AltOptionsSer>, JsValue> {
+    serde_wasm_bindgen::from_value(alt)
+        .map_err(|e| JsValue::from_str(&format!("failed to deserialize alt: {}", e)))
+}
+
+fn finish_inline(
+    svg: String,
+    alt: Option<&options::AltOptionsSer>,
+    code: &str,
+    kind: &str,
+    prefix: &str,
+    sheet: Option<&mut GlyphSheet>,
+) -> String {
+    let svg = match sheet {
+        Some(sheet) => sheet.share(&svg, prefix),
+        None => utils::prefix_ids(&svg, |_| prefix),
+    };
+
+    match alt {
+        Some(alt) => {
+            let math = kind == "inline" || kind == "display";
+            describe::embed(&svg, &alt.describe(code, math))
+        }
+        None => svg,
+    }
+}
+
+// ? 2 ページ目以降は p1-, p2-, ... を prefix に足す
+fn finish_pages(
+    pages: Vec<String>,
+    alt: Option<&options::AltOptionsSer>,
+    code: &str,
+    kind: &str,
+    prefix: &str,
+    mut sheet: Option<&mut GlyphSheet>,
+) -> Vec<String> {
+    pages
+        .into_iter()
+        .enumerate()
+        .map(|(i, svg)| {
+            let page_prefix = format!("{}p{}-", prefix, i + 1);
+            finish_inline(svg, alt, code, kind, &page_prefix, sheet.as_deref_mut())
+        })
+        .collect()
+}
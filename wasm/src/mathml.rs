@@ -0,0 +1,387 @@
+use typst::{
+    diag::SourceDiagnostic,
+    ecow::{EcoVec, eco_format},
+    foundations::{Content, Value},
+};
+
+// ? 装飾要素とその記号
+const DECORATIONS: &[(&str, &str, bool)] = &[
+    ("overline", "‾", true),
+    ("underline", "_", false),
+    ("overbrace", "⏞", true),
+    ("underbrace", "⏟", false),
+    ("overbracket", "⎴", true),
+    ("underbracket", "⎵", false),
+    ("overparen", "⏜", true),
+    ("underparen", "⏝", false),
+    ("overshell", "⏠", true),
+    ("undershell", "⏡", false),
+];
+
+// ? 数式の本体 (EquationElem の body) から Presentation MathML を生成する
+pub fn convert(body: &Content, display: bool) -> (String, EcoVec<SourceDiagnostic>) {
+    let mut writer = Writer {
+        out: String::new(),
+        warnings: EcoVec::new(),
+    };
+
+    writer.out.push_str(&format!(
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"{}\">",
+        if display { "block" } else { "inline" }
+    ));
+    writer.row(body);
+    writer.out.push_str("</math>");
+
+    (writer.out, writer.warnings)
+}
+
+struct Writer {
+    out: String,
+    warnings: EcoVec<SourceDiagnostic>,
+}
+
+impl Writer {
+    // ? 子要素をまとめて 1 つの引数にする
+    fn row(&mut self, content: &Content) {
+        let children = flatten(content);
+        if children.len() == 1 {
+            self.node(&children[0]);
+            return;
+        }
+
+        self.out.push_str("<mrow>");
+        for child in &children {
+            self.node(child);
+        }
+        self.out.push_str("</mrow>");
+    }
+
+    fn optional_row(&mut self, content: Option<&Content>) {
+        match content {
+            Some(content) => self.row(content),
+            None => self.out.push_str("<none/>"),
+        }
+    }
+
+    fn node(&mut self, content: &Content) {
+        let name = content.elem().name();
+        match name {
+            "sequence" => self.row(content),
+            "styled" | "class" | "mid" | "limits" | "scripts" | "equation" => {
+                if let Some(body) = field(content, "child").or_else(|| field(content, "body")) {
+                    self.row(&body);
+                }
+            }
+            "space" | "align-point" => {}
+            "linebreak" => self.out.push_str("<mspace linebreak=\"newline\"/>"),
+            "h" => self.out.push_str("<mspace width=\"0.2em\"/>"),
+            "text" | "symbol" => {
+                if let Some(text) = string(content, "text") {
+                    self.token(&text, "");
+                }
+            }
+            "op" => {
+                let text = field(content, "text")
+                    .map(|c| c.plain_text().to_string())
+                    .unwrap_or_default();
+                self.token_as("mi", &text, "");
+            }
+            "frac" => {
+                self.out.push_str("<mfrac>");
+                self.optional_row(field(content, "num").as_ref());
+                self.optional_row(field(content, "denom").as_ref());
+                self.out.push_str("</mfrac>");
+            }
+            "binom" => {
+                self.out
+                    .push_str("<mrow><mo fence=\"true\" form=\"prefix\">(</mo>");
+                self.out.push_str("<mfrac linethickness=\"0\">");
+                self.optional_row(field(content, "upper").as_ref());
+                match content.get_by_name("lower") {
+                    Ok(Value::Array(lower)) => {
+                        self.out.push_str("<mrow>");
+                        for (i, value) in lower.iter().enumerate() {
+                            if i > 0 {
+                                self.out.push_str("<mo separator=\"true\">,</mo>");
+                            }
+                            if let Value::Content(c) = value {
+                                self.row(c);
+                            }
+                        }
+                        self.out.push_str("</mrow>");
+                    }
+                    _ => self.optional_row(field(content, "lower").as_ref()),
+                }
+                self.out
+                    .push_str("</mfrac><mo fence=\"true\" form=\"postfix\">)</mo></mrow>");
+            }
+            "root" => match field(content, "index") {
+                Some(index) => {
+                    self.out.push_str("<mroot>");
+                    self.optional_row(field(content, "radicand").as_ref());
+                    self.row(&index);
+                    self.out.push_str("</mroot>");
+                }
+                None => {
+                    self.out.push_str("<msqrt>");
+                    self.optional_row(field(content, "radicand").as_ref());
+                    self.out.push_str("</msqrt>");
+                }
+            },
+            "attach" => self.attach(content),
+            "primes" => {
+                let count = match content.get_by_name("count") {
+                    Ok(Value::Int(count)) => count.max(1) as usize,
+                    _ => 1,
+                };
+                self.token_as("mo", &"′".repeat(count), "");
+            }
+            "accent" => {
+                let accent = string(content, "accent").unwrap_or_default();
+                self.out.push_str("<mover accent=\"true\">");
+                self.optional_row(field(content, "base").as_ref());
+                self.token_as("mo", &accent, " stretchy=\"false\"");
+                self.out.push_str("</mover>");
+            }
+            "lr" => {
+                let children = field(content, "body")
+                    .map(|c| flatten(&c))
+                    .unwrap_or_default();
+                let last = children.len().saturating_sub(1);
+                self.out.push_str("<mrow>");
+                for (i, child) in children.iter().enumerate() {
+                    let form = if i == 0 {
+                        Some("prefix")
+                    } else if i == last {
+                        Some("postfix")
+                    } else {
+                        None
+                    };
+                    match (form, string(child, "text")) {
+                        (Some(form), Some(text)) if is_fence(&text) => self.token_as(
+                            "mo",
+                            &text,
+                            &format!(" fence=\"true\" stretchy=\"true\" form=\"{}\"", form),
+                        ),
+                        _ => self.node(child),
+                    }
+                }
+                self.out.push_str("</mrow>");
+            }
+            "mat" => {
+                let rows: Vec<Vec<Content>> = match content.get_by_name("rows") {
+                    Ok(Value::Array(rows)) => rows
+                        .iter()
+                        .map(|row| match row {
+                            Value::Array(cells) => cells.iter().filter_map(as_content).collect(),
+                            other => as_content(other).into_iter().collect(),
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                self.table(&rows, "(", ")");
+            }
+            "vec" => {
+                let rows: Vec<Vec<Content>> = children(content)
+                    .into_iter()
+                    .map(|child| vec![child])
+                    .collect();
+                self.table(&rows, "(", ")");
+            }
+            "cases" => {
+                let rows: Vec<Vec<Content>> = children(content)
+                    .into_iter()
+                    .map(|child| vec![child])
+                    .collect();
+                self.table(&rows, "{", "");
+            }
+            "cancel" => {
+                self.out
+                    .push_str("<menclose notation=\"updiagonalstrike\">");
+                self.optional_row(field(content, "body").as_ref());
+                self.out.push_str("</menclose>");
+            }
+            _ => {
+                if let Some((_, symbol, over)) = DECORATIONS.iter().find(|(n, ..)| *n == name) {
+                    self.decoration(content, symbol, *over);
+                    return;
+                }
+
+                // ? 対応していない要素はテキストとして出力する
+                self.warnings.push(SourceDiagnostic::warning(
+                    content.span(),
+                    eco_format!("{} is not supported in MathML output", name),
+                ));
+                self.token_as("mtext", &content.plain_text(), "");
+            }
+        }
+    }
+
+    fn attach(&mut self, content: &Content) {
+        let base = field(content, "base");
+        let t = field(content, "t").or_else(|| field(content, "tr"));
+        let b = field(content, "b").or_else(|| field(content, "br"));
+        let tl = field(content, "tl");
+        let bl = field(content, "bl");
+
+        if tl.is_some() || bl.is_some() {
+            self.out.push_str("<mmultiscripts>");
+            self.optional_row(base.as_ref());
+            self.optional_row(b.as_ref());
+            self.optional_row(t.as_ref());
+            self.out.push_str("<mprescripts/>");
+            self.optional_row(bl.as_ref());
+            self.optional_row(tl.as_ref());
+            self.out.push_str("</mmultiscripts>");
+            return;
+        }
+
+        let limits = base.as_ref().is_some_and(has_limits);
+        let (tag, under, over) = match (limits, &b, &t) {
+            (true, Some(_), Some(_)) => ("munderover", true, true),
+            (true, Some(_), None) => ("munder", true, false),
+            (true, None, Some(_)) => ("mover", false, true),
+            (false, Some(_), Some(_)) => ("msubsup", true, true),
+            (false, Some(_), None) => ("msub", true, false),
+            (false, None, Some(_)) => ("msup", false, true),
+            (_, None, None) => {
+                self.optional_row(base.as_ref());
+                return;
+            }
+        };
+
+        self.out.push_str(&format!("<{}>", tag));
+        self.optional_row(base.as_ref());
+        if under {
+            self.optional_row(b.as_ref());
+        }
+        if over {
+            self.optional_row(t.as_ref());
+        }
+        self.out.push_str(&format!("</{}>", tag));
+    }
+
+    fn table(&mut self, rows: &[Vec<Content>], open: &str, close: &str) {
+        self.out.push_str("<mrow>");
+        if !open.is_empty() {
+            self.token_as("mo", open, " fence=\"true\" form=\"prefix\"");
+        }
+        self.out.push_str("<mtable>");
+        for row in rows {
+            self.out.push_str("<mtr>");
+            for cell in row {
+                self.out.push_str("<mtd>");
+                self.row(cell);
+                self.out.push_str("</mtd>");
+            }
+            self.out.push_str("</mtr>");
+        }
+        self.out.push_str("</mtable>");
+        if !close.is_empty() {
+            self.token_as("mo", close, " fence=\"true\" form=\"postfix\"");
+        }
+        self.out.push_str("</mrow>");
+    }
+
+    fn decoration(&mut self, content: &Content, symbol: &str, over: bool) {
+        let tag = if over { "mover" } else { "munder" };
+        let annotation = field(content, "annotation");
+
+        if annotation.is_some() {
+            self.out.push_str(&format!("<{}>", tag));
+        }
+        self.out.push_str(&format!("<{} accent=\"true\">", tag));
+        self.optional_row(field(content, "body").as_ref());
+        self.token_as("mo", symbol, " stretchy=\"true\"");
+        self.out.push_str(&format!("</{}>", tag));
+        if let Some(annotation) = annotation {
+            self.row(&annotation);
+            self.out.push_str(&format!("</{}>", tag));
+        }
+    }
+
+    fn token(&mut self, text: &str, attrs: &str) {
+        let tag = if text.chars().all(|c| c.is_ascii_digit() || c == '.')
+            && text.starts_with(|c: char| c.is_ascii_digit())
+        {
+            "mn"
+        } else if text.chars().all(char::is_alphabetic) {
+            "mi"
+        } else if text.chars().count() == 1 || text.chars().all(|c| !c.is_alphanumeric()) {
+            "mo"
+        } else {
+            "mtext"
+        };
+        self.token_as(tag, text, attrs);
+    }
+
+    fn token_as(&mut self, tag: &str, text: &str, attrs: &str) {
+        self.out.push_str(&format!("<{}{}>", tag, attrs));
+        for c in text.chars() {
+            match c {
+                '&' => self.out.push_str("&amp;"),
+                '<' => self.out.push_str("&lt;"),
+                '>' => self.out.push_str("&gt;"),
+                '"' => self.out.push_str("&quot;"),
+                c => self.out.push(c),
+            }
+        }
+        self.out.push_str(&format!("</{}>", tag));
+    }
+}
+
+// ? sequence を展開し，空白を除く
+fn flatten(content: &Content) -> Vec<Content> {
+    if content.elem().name() != "sequence" {
+        return vec![content.clone()];
+    }
+
+    children(content)
+        .iter()
+        .flat_map(flatten)
+        .filter(|child| child.elem().name() != "space")
+        .collect()
+}
+
+fn children(content: &Content) -> Vec<Content> {
+    match content.get_by_name("children") {
+        Ok(Value::Array(children)) => children.iter().filter_map(as_content).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn field(content: &Content, name: &str) -> Option<Content> {
+    as_content(&content.get_by_name(name).ok()?)
+}
+
+fn as_content(value: &Value) -> Option<Content> {
+    match value {
+        Value::Content(content) => Some(content.clone()),
+        Value::None => None,
+        other => Some(other.clone().display()),
+    }
+}
+
+fn string(content: &Content, name: &str) -> Option<String> {
+    match content.get_by_name(name).ok()? {
+        Value::Str(s) => Some(s.to_string()),
+        Value::Symbol(s) => Some(s.get().to_string()),
+        _ => None,
+    }
+}
+
+fn has_limits(base: &Content) -> bool {
+    match base.elem().name() {
+        "limits" => true,
+        "op" => matches!(base.get_by_name("limits"), Ok(Value::Bool(true))),
+        "styled" => field(base, "child").is_some_and(|child| has_limits(&child)),
+        _ => false,
+    }
+}
+
+fn is_fence(text: &str) -> bool {
+    matches!(
+        text,
+        "(" | ")" | "[" | "]" | "{" | "}" | "|" | "‖" | "⟨" | "⟩" | "⌊" | "⌋" | "⌈" | "⌉"
+    )
+}
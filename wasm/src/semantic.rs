@@ -0,0 +1,172 @@
+use typst::syntax::{
+    Lines, LinkedNode, Source, SyntaxKind, SyntaxNode, Tag, highlight, parse, parse_code,
+    parse_math,
+};
+
+// ? Tag::LIST の後ろに追加する種類
+pub const SHORTHAND: u32 = Tag::LIST.len() as u32;
+pub const SYMBOL: u32 = SHORTHAND + 1;
+
+pub const MODIFIER_MATH: u32 = 1 << 0;
+pub const MODIFIER_STRONG: u32 = 1 << 1;
+pub const MODIFIER_EMPH: u32 = 1 << 2;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mode {
+    Markup,
+    Code,
+    Math,
+}
+
+impl Mode {
+    pub fn from_name(mode: &str) -> Self {
+        match mode {
+            "code" => Mode::Code,
+            "math" => Mode::Math,
+            _ => Mode::Markup,
+        }
+    }
+
+    pub fn parse(&self, text: &str) -> SyntaxNode {
+        match self {
+            Mode::Markup => parse(text),
+            Mode::Code => parse_code(text),
+            Mode::Math => parse_math(text),
+        }
+    }
+}
+
+// ? 差分を適用するために前回のテキストを保持する．
+// ? Source は Markup としてパースされるため，差分パースが効くのは Markup のみ．
+// ? Code と Math は行情報だけを更新し，毎回パースし直す
+pub enum SemanticSource {
+    Markup(Source),
+    Detached(Lines<String>, Mode),
+}
+
+impl SemanticSource {
+    pub fn new(text: &str, mode: Mode) -> Self {
+        match mode {
+            Mode::Markup => Self::Markup(Source::detached(text)),
+            _ => Self::Detached(Lines::new(text.to_string()), mode),
+        }
+    }
+
+    // ? from, to は UTF-16
+    pub fn edit(&mut self, from: usize, to: usize, text: &str) -> Option<()> {
+        let lines = match self {
+            Self::Markup(source) => source.lines(),
+            Self::Detached(lines, _) => &*lines,
+        };
+        let from = lines.utf16_to_byte(from)?;
+        let to = lines.utf16_to_byte(to)?;
+
+        match self {
+            Self::Markup(source) => {
+                source.edit(from..to, text);
+            }
+            Self::Detached(lines, _) => {
+                lines.edit(from..to, text);
+            }
+        }
+
+        Some(())
+    }
+
+    pub fn tokens(&self) -> Vec<u32> {
+        match self {
+            Self::Markup(source) => tokens(source.root(), source.lines(), Mode::Markup),
+            Self::Detached(lines, mode) => tokens(&mode.parse(lines.text()), lines, *mode),
+        }
+    }
+}
+
+pub fn token_types() -> Vec<String> {
+    let mut types: Vec<String> = Tag::LIST.iter().map(|tag| format!("{:?}", tag)).collect();
+    types.push("Shorthand".into());
+    types.push("Symbol".into());
+    types
+}
+
+pub fn token_modifiers() -> Vec<String> {
+    vec!["Math".into(), "Strong".into(), "Emph".into()]
+}
+
+// ? [開始位置の差分, 長さ, 種類, 修飾子] の 4 つ組を並べる (UTF-16)
+pub fn tokens(root: &SyntaxNode, lines: &Lines<String>, mode: Mode) -> Vec<u32> {
+    let mut encoder = Encoder {
+        lines,
+        last: 0,
+        data: Vec::new(),
+    };
+
+    let modifiers = if mode == Mode::Math { MODIFIER_MATH } else { 0 };
+    encoder.node(&LinkedNode::new(root), None, modifiers);
+
+    encoder.data
+}
+
+struct Encoder<'a> {
+    lines: &'a Lines<String>,
+    last: usize,
+    data: Vec<u32>,
+}
+
+impl Encoder<'_> {
+    fn node(&mut self, node: &LinkedNode, inherited: Option<u32>, mut modifiers: u32) {
+        let tag = highlight(node);
+
+        if node.children().len() == 0 {
+            let ty = match (node.kind(), tag) {
+                (SyntaxKind::Space | SyntaxKind::Parbreak, _) => None,
+                (SyntaxKind::Shorthand, _) => Some(SHORTHAND),
+                (SyntaxKind::MathIdent, Some(Tag::Interpolated)) => Some(SYMBOL),
+                (_, Some(tag)) => Some(tag as u32),
+                (_, None) => inherited,
+            };
+            if let Some(ty) = ty {
+                self.push(node, ty, modifiers);
+            }
+            return;
+        }
+
+        let inherited = match tag {
+            Some(Tag::Strong) => {
+                modifiers |= MODIFIER_STRONG;
+                inherited
+            }
+            Some(Tag::Emph) => {
+                modifiers |= MODIFIER_EMPH;
+                inherited
+            }
+            Some(tag) => Some(tag as u32),
+            None => inherited,
+        };
+        if matches!(node.kind(), SyntaxKind::Equation | SyntaxKind::Math) {
+            modifiers |= MODIFIER_MATH;
+        }
+
+        for child in node.children() {
+            self.node(&child, inherited, modifiers);
+        }
+    }
+
+    fn push(&mut self, node: &LinkedNode, ty: u32, modifiers: u32) {
+        let range = node.range();
+        let (Some(start), Some(end)) = (
+            self.lines.byte_to_utf16(range.start),
+            self.lines.byte_to_utf16(range.end),
+        ) else {
+            return;
+        };
+        if start == end {
+            return;
+        }
+
+        self.data.push((start - self.last) as u32);
+        self.data.push((end - start) as u32);
+        self.data.push(ty);
+        self.data.push(modifiers);
+        self.last = start;
+    }
+}
@@ -0,0 +1,57 @@
+use serde::Serialize;
+
+use typst::syntax::Source;
+use typst_ide::{Completion, CompletionKind};
+
+#[derive(Serialize)]
+pub struct CompletionSer {
+    pub label: String,
+    pub kind: String,
+    pub apply: Option<String>,
+    pub detail: Option<String>,
+    pub symbol: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CompletionsSer {
+    pub from: usize,
+    pub to: usize,
+    pub completions: Vec<CompletionSer>,
+}
+
+impl From<&Completion> for CompletionSer {
+    fn from(completion: &Completion) -> Self {
+        let (kind, symbol) = match &completion.kind {
+            CompletionKind::Syntax => ("syntax", None),
+            CompletionKind::Func => ("func", None),
+            CompletionKind::Type => ("type", None),
+            CompletionKind::Param => ("param", None),
+            CompletionKind::Constant => ("constant", None),
+            CompletionKind::Path => ("path", None),
+            CompletionKind::Package => ("package", None),
+            CompletionKind::Label => ("label", None),
+            CompletionKind::Font => ("font", None),
+            CompletionKind::Symbol(s) => ("symbol", Some(s.to_string())),
+        };
+
+        CompletionSer {
+            label: completion.label.to_string(),
+            kind: kind.to_string(),
+            apply: completion.apply.as_ref().map(|a| a.to_string()),
+            detail: completion.detail.as_ref().map(|d| d.to_string()),
+            symbol,
+        }
+    }
+}
+
+impl CompletionsSer {
+    pub fn new(from: usize, to: usize, completions: &[Completion], source: &Source) -> Self {
+        let lines = source.lines();
+
+        CompletionsSer {
+            from: lines.byte_to_utf16(from).unwrap_or(0),
+            to: lines.byte_to_utf16(to).unwrap_or(0),
+            completions: completions.iter().map(Into::into).collect(),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use serde::Serialize;
+
+use crate::formatter::FormatError;
+
+#[derive(Serialize)]
+pub struct FormatErrorSer {
+    pub message: String,
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Serialize)]
+pub struct FormatResultSer {
+    pub text: String,
+    pub errors: Vec<FormatErrorSer>,
+}
+
+impl FormatResultSer {
+    pub fn from_result(code: &str, result: Result<String, Vec<FormatError>>) -> Self {
+        match result {
+            Ok(text) => FormatResultSer {
+                text,
+                errors: Vec::new(),
+            },
+            // ? 構文エラーがあるときは元のテキストを返す
+            Err(errors) => FormatResultSer {
+                text: code.to_string(),
+                errors: errors
+                    .iter()
+                    .map(|e| FormatErrorSer {
+                        message: e.message.clone(),
+                        from: utf16_len(code, e.range.start),
+                        to: utf16_len(code, e.range.end),
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+fn utf16_len(code: &str, byte: usize) -> usize {
+    code.get(..byte).map_or(0, |s| s.encode_utf16().count())
+}
@@ -0,0 +1,20 @@
+use serde::Serialize;
+use serde_wasm_bindgen::to_value;
+use wasm_bindgen::JsValue;
+
+use crate::budget::Interrupted;
+
+#[derive(Serialize)]
+struct InterruptedSer {
+    interrupted: Interrupted,
+    message: &'static str,
+}
+
+// ? 診断の配列と区別できるよう，オブジェクトで返す
+pub fn interrupted(interrupted: Interrupted) -> JsValue {
+    let result = InterruptedSer {
+        interrupted,
+        message: interrupted.message(),
+    };
+    to_value(&result).unwrap_or(JsValue::NULL)
+}
@@ -1,6 +1,8 @@
+use std::ops::Range;
+
 use serde::Serialize;
 
-use typst::World;
+use typst::{World, WorldExt, syntax::Span};
 use typst_ide::Jump;
 
 #[derive(Serialize)]
@@ -10,6 +12,7 @@ pub enum JumpSer {
         package: Option<String>,
         path: String,
         pos: Option<usize>,
+        range: Option<Range<usize>>,
     },
     Url {
         url: String,
@@ -38,6 +41,7 @@ impl JumpSer {
                     package,
                     path,
                     pos: Some(pos),
+                    range: None,
                 }
             }
             Jump::Url(url) => JumpSer::Url {
@@ -47,6 +51,28 @@ impl JumpSer {
         }
     }
 
+    pub fn from_span<W>(span: Span, world: &W) -> Option<Self>
+    where
+        W: World,
+    {
+        let id = span.id()?;
+        let package = id.package().map(|spec| spec.to_string());
+        let path = id.vpath().as_rootless_path().to_string_lossy().to_string();
+
+        let source = world.source(id).ok()?;
+        let range = world.range(span)?;
+        let lines = source.lines();
+        let start = lines.byte_to_utf16(range.start).unwrap_or(0);
+        let end = lines.byte_to_utf16(range.end).unwrap_or(start);
+
+        Some(JumpSer::File {
+            package,
+            path,
+            pos: Some(start),
+            range: Some(start..end),
+        })
+    }
+
     pub fn from_position(pos: typst::layout::Position) -> Self {
         JumpSer::Position {
             page: pos.page.get(),
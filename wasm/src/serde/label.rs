@@ -0,0 +1,110 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Serialize;
+
+use typst::{
+    foundations::{Content, Label},
+    layout::PagedDocument,
+    syntax::{LinkedNode, Source, SyntaxKind, ast},
+};
+
+#[derive(Serialize)]
+pub struct LabelSer {
+    pub name: String,
+    pub kind: Option<String>,
+    pub detail: Option<String>,
+    pub page: Option<usize>,
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct DanglingRefSer {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Serialize)]
+pub struct LabelsSer {
+    pub labels: Vec<LabelSer>,
+    pub dangling: Vec<DanglingRefSer>,
+}
+
+impl LabelsSer {
+    pub fn new(document: &PagedDocument, source: &Source) -> Self {
+        let (analyzed, split) = typst_ide::analyze_labels(document);
+        let elems: FxHashMap<Label, &Content> = document
+            .introspector
+            .all()
+            .filter_map(|c| c.label().map(|label| (label, c)))
+            .collect();
+
+        let mut labels: Vec<LabelSer> = Vec::with_capacity(analyzed.len());
+        for (i, (label, detail)) in analyzed.iter().enumerate() {
+            let name = label.resolve().as_str().to_string();
+            let detail = detail.as_ref().map(|d| d.to_string());
+
+            // ? split 以降は参考文献のキー
+            if i >= split {
+                labels.push(LabelSer {
+                    name,
+                    kind: Some("bibliography".into()),
+                    detail,
+                    page: None,
+                    x: None,
+                    y: None,
+                });
+                continue;
+            }
+
+            let elem = elems.get(label).copied();
+            let position = elem
+                .and_then(|c| c.location())
+                .map(|loc| document.introspector.position(loc));
+
+            labels.push(LabelSer {
+                name,
+                kind: elem.map(|c| c.elem().name().to_string()),
+                detail,
+                page: position.as_ref().map(|p| p.page.get()),
+                x: position.as_ref().map(|p| p.point.x.to_pt()),
+                y: position.as_ref().map(|p| p.point.y.to_pt()),
+            });
+        }
+
+        let names: FxHashSet<&str> = labels.iter().map(|l| l.name.as_str()).collect();
+        let mut dangling = Vec::new();
+        collect_refs(
+            &LinkedNode::new(source.root()),
+            source,
+            &names,
+            &mut dangling,
+        );
+
+        LabelsSer { labels, dangling }
+    }
+}
+
+fn collect_refs(
+    node: &LinkedNode,
+    source: &Source,
+    names: &FxHashSet<&str>,
+    out: &mut Vec<DanglingRefSer>,
+) {
+    if node.kind() == SyntaxKind::Ref
+        && let Some(reference) = node.cast::<ast::Ref>()
+        && !names.contains(reference.target())
+    {
+        let lines = source.lines();
+        let range = node.range();
+        out.push(DanglingRefSer {
+            name: reference.target().to_string(),
+            from: lines.byte_to_utf16(range.start).unwrap_or(0),
+            to: lines.byte_to_utf16(range.end).unwrap_or(0),
+        });
+    }
+
+    for child in node.children() {
+        collect_refs(&child, source, names, out);
+    }
+}
@@ -0,0 +1,37 @@
+use ecow::EcoVec;
+use serde::Serialize;
+use serde_wasm_bindgen::to_value;
+use wasm_bindgen::JsValue;
+
+use typst::{diag::SourceDiagnostic, ecow};
+
+use crate::serde::diagnostic::SourceDiagnosticSer;
+use crate::world::WasmWorld;
+
+#[derive(Serialize)]
+struct MathmlSer {
+    mathml: String,
+    diags: Vec<SourceDiagnosticSer>,
+}
+
+// ? prefix は code の前に付けた $ などの長さ
+pub fn mathml(
+    mathml: String,
+    diags: EcoVec<SourceDiagnostic>,
+    prefix: usize,
+    world: &WasmWorld,
+) -> Result<JsValue, JsValue> {
+    let result = MathmlSer {
+        mathml,
+        diags: diags
+            .iter()
+            .map(|d| {
+                let mut diag = SourceDiagnosticSer::from_diag(d, world);
+                diag.from = diag.from.saturating_sub(prefix);
+                diag.to = diag.to.saturating_sub(prefix);
+                diag
+            })
+            .collect(),
+    };
+    Ok(to_value(&result)?)
+}
@@ -1,11 +1,24 @@
+pub mod completion;
 pub mod diagnostic;
 pub mod font;
+pub mod format;
 pub mod jump;
+pub mod label;
+pub mod mathml;
+pub mod outline;
 pub mod package;
+pub mod reference;
+pub mod rename;
+pub mod semantic;
+pub mod signature;
+pub mod tooltip;
 
+pub mod htmlr;
+pub mod interrupted;
 pub mod options;
 pub mod pdfr;
 pub mod pngr;
 pub mod svg;
+pub mod svg_batch;
 pub mod svgp;
 pub mod svgr;
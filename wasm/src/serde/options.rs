@@ -1,5 +1,8 @@
 use serde::Deserialize;
 
+use crate::colors::{self, ColorMapping};
+use crate::describe::{self, AltMode};
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PdfOptionsSer {
@@ -15,6 +18,72 @@ pub struct PdfOptionsSer {
 #[serde(rename_all = "camelCase")]
 pub struct SvgOptionsSer {
     pub page_ranges: Option<String>,
+    #[serde(default)]
+    pub crop: bool,
+    // ? pt
+    #[serde(default)]
+    pub crop_padding: f64,
+    #[serde(default)]
+    pub alt: Option<AltOptionsSer>,
+    pub id_prefix: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaletteEntrySer {
+    pub color: String,
+    // ? 例: "var(--text-normal)"
+    pub css: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorMappingSer {
+    #[serde(default)]
+    pub palette: Vec<PaletteEntrySer>,
+    #[serde(default)]
+    pub invert_lightness: bool,
+}
+
+impl ColorMappingSer {
+    pub fn to_mapping(&self) -> Result<ColorMapping, String> {
+        let palette = self
+            .palette
+            .iter()
+            .map(|entry| Ok((colors::parse_color(&entry.color)?, entry.css.clone())))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(ColorMapping::new(palette, self.invert_lightness))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AltOptionsSer {
+    pub mode: AltMode,
+    // ? 省略時は，数式ならコンパイルしたソースの最後の数式，それ以外はソース全体を使う
+    pub source: Option<String>,
+}
+
+impl AltOptionsSer {
+    pub fn describe(&self, fallback: &str, math: bool) -> String {
+        if let Some(source) = &self.source {
+            return describe::describe(source, self.mode, math);
+        }
+
+        match math.then(|| describe::formula(fallback)).flatten() {
+            Some(formula) => describe::describe(&formula, self.mode, true),
+            None => describe::describe(fallback, self.mode, false),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HtmlOptionsSer {
+    // ? 数式を html.frame で SVG として埋め込む
+    #[serde(default = "default_true")]
+    pub math_svg: bool,
 }
 
 #[derive(Deserialize)]
@@ -22,4 +91,41 @@ pub struct SvgOptionsSer {
 pub struct PngOptionsSer {
     pub ppi: f32,
     pub page_ranges: Option<String>,
+    #[serde(default)]
+    pub crop: bool,
+    // ? pt
+    #[serde(default)]
+    pub crop_padding: f64,
+    // ? "transparent", "page" または色
+    pub background: Option<String>,
+    // ? px, ppi より小さい倍率になる場合のみ適用
+    pub max_width: Option<f64>,
+    pub max_height: Option<f64>,
+    // ? インライン SVG の --typst-base-color に相当する色
+    pub base_color: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatOptionsSer {
+    #[serde(default = "default_indent_width")]
+    pub indent_width: usize,
+    #[serde(default = "default_max_width")]
+    pub max_width: usize,
+    #[serde(default = "default_true")]
+    pub math_operator_spacing: bool,
+    #[serde(default)]
+    pub math: bool,
+}
+
+fn default_indent_width() -> usize {
+    2
+}
+
+fn default_max_width() -> usize {
+    80
+}
+
+fn default_true() -> bool {
+    true
 }
@@ -0,0 +1,216 @@
+use comemo::Track;
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+
+use typst::{
+    ROUTINES, World,
+    engine::{Engine, Route, Sink, Traced},
+    foundations::{Element, FromValue, NativeElement, Selector, StyleChain},
+    introspection::Counter,
+    layout::PagedDocument,
+    model::{HeadingElem, Numbering},
+    syntax::{
+        LinkedNode, Source, Span, SyntaxKind,
+        ast::{self, AstNode},
+    },
+};
+
+#[derive(Serialize)]
+pub struct DocumentSymbolSer {
+    pub name: String,
+    pub kind: String,
+    pub level: Option<usize>,
+    pub number: Option<String>,
+    pub page: Option<usize>,
+    pub from: usize,
+    pub to: usize,
+    pub children: Vec<DocumentSymbolSer>,
+}
+
+struct HeadingInfo {
+    number: Option<String>,
+    page: usize,
+}
+
+pub fn document_symbols(
+    source: &Source,
+    document: Option<&PagedDocument>,
+    world: &dyn World,
+) -> Vec<DocumentSymbolSer> {
+    let headings = document
+        .map(|document| heading_infos(document, world))
+        .unwrap_or_default();
+
+    let mut flat = Vec::new();
+    collect(
+        &LinkedNode::new(source.root()),
+        source,
+        &headings,
+        &mut flat,
+    );
+
+    // ? 見出しのレベルに従って入れ子にする
+    let mut roots: Vec<DocumentSymbolSer> = Vec::new();
+    let mut stack: Vec<DocumentSymbolSer> = Vec::new();
+    for symbol in flat {
+        if let Some(level) = symbol.level {
+            while stack.last().is_some_and(|s| s.level.unwrap_or(0) >= level) {
+                let done = stack.pop().unwrap();
+                push_symbol(&mut stack, &mut roots, done);
+            }
+            stack.push(symbol);
+        } else {
+            push_symbol(&mut stack, &mut roots, symbol);
+        }
+    }
+    while let Some(done) = stack.pop() {
+        push_symbol(&mut stack, &mut roots, done);
+    }
+
+    roots
+}
+
+fn push_symbol(
+    stack: &mut [DocumentSymbolSer],
+    roots: &mut Vec<DocumentSymbolSer>,
+    symbol: DocumentSymbolSer,
+) {
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(symbol),
+        None => roots.push(symbol),
+    }
+}
+
+fn collect(
+    node: &LinkedNode,
+    source: &Source,
+    headings: &FxHashMap<Span, HeadingInfo>,
+    out: &mut Vec<DocumentSymbolSer>,
+) {
+    let lines = source.lines();
+    let range = node.range();
+    let symbol = |name: String, kind: &str| DocumentSymbolSer {
+        name,
+        kind: kind.to_string(),
+        level: None,
+        number: None,
+        page: None,
+        from: lines.byte_to_utf16(range.start).unwrap_or(0),
+        to: lines.byte_to_utf16(range.end).unwrap_or(0),
+        children: Vec::new(),
+    };
+
+    match node.kind() {
+        SyntaxKind::Heading => {
+            if let Some(heading) = node.cast::<ast::Heading>() {
+                let mut s = symbol(node_text(heading.body()), "heading");
+                s.level = Some(heading.depth().get());
+                if let Some(info) = headings.get(&node.span()) {
+                    s.number = info.number.clone();
+                    s.page = Some(info.page);
+                }
+                out.push(s);
+            }
+        }
+        SyntaxKind::LetBinding => {
+            if let Some(binding) = node.cast::<ast::LetBinding>() {
+                match binding.kind() {
+                    ast::LetBindingKind::Closure(ident) => {
+                        out.push(symbol(ident.as_str().to_string(), "function"));
+                    }
+                    ast::LetBindingKind::Normal(pattern) => {
+                        for ident in pattern.bindings() {
+                            out.push(symbol(ident.as_str().to_string(), "variable"));
+                        }
+                    }
+                }
+            }
+        }
+        SyntaxKind::Label => {
+            if let Some(label) = node.cast::<ast::Label>() {
+                out.push(symbol(label.get().to_string(), "label"));
+            }
+        }
+        SyntaxKind::FuncCall => {
+            if let Some(call) = node.cast::<ast::FuncCall>()
+                && let ast::Expr::Ident(ident) = call.callee()
+                && ident.as_str() == "figure"
+            {
+                out.push(symbol(figure_name(call), "figure"));
+            }
+        }
+        SyntaxKind::Equation => {
+            if let Some(equation) = node.cast::<ast::Equation>()
+                && equation.block()
+            {
+                out.push(symbol(node_text(equation.body()), "equation"));
+            }
+        }
+        _ => {}
+    }
+
+    for child in node.children() {
+        collect(&child, source, headings, out);
+    }
+}
+
+// ? 番号は見出しのカウンタを各見出しの位置で読み，その見出しの numbering で表示したもの
+fn heading_infos(document: &PagedDocument, world: &dyn World) -> FxHashMap<Span, HeadingInfo> {
+    let traced = Traced::default();
+    let mut sink = Sink::new();
+    let mut engine = Engine {
+        routines: &ROUTINES,
+        world: world.track(),
+        introspector: document.introspector.track(),
+        traced: traced.track(),
+        sink: sink.track_mut(),
+        route: Route::default(),
+    };
+
+    let counter = Counter::of(HeadingElem::ELEM);
+    let selector = Selector::Elem(Element::of::<HeadingElem>(), None);
+    let mut infos = FxHashMap::default();
+
+    for content in document.introspector.query(&selector).iter() {
+        let Some(loc) = content.location() else {
+            continue;
+        };
+
+        let number = content
+            .get_by_name("numbering")
+            .ok()
+            .and_then(|value| Option::<Numbering>::from_value(value).ok().flatten())
+            .and_then(|numbering| {
+                counter
+                    .display_at_loc(&mut engine, loc, StyleChain::default(), &numbering)
+                    .ok()
+            })
+            .map(|number| number.plain_text().trim().to_string());
+        let page = document.introspector.page(loc).get();
+
+        infos.insert(content.span(), HeadingInfo { number, page });
+    }
+
+    infos
+}
+
+fn figure_name(call: ast::FuncCall) -> String {
+    for arg in call.args().items() {
+        if let ast::Arg::Named(named) = arg
+            && named.name().as_str() == "caption"
+        {
+            return node_text(named.expr())
+                .trim_matches(|c| c == '[' || c == ']')
+                .trim()
+                .to_string();
+        }
+    }
+    "figure".to_string()
+}
+
+fn node_text<'a, N>(node: N) -> String
+where
+    N: AstNode<'a>,
+{
+    node.to_untyped().clone().into_text().trim().to_string()
+}
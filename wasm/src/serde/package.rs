@@ -1,6 +1,11 @@
-use serde::Serialize;
+use std::str::FromStr;
 
-use typst::syntax::package::PackageSpec;
+use serde::{Deserialize, Serialize};
+
+use typst::{
+    ecow::EcoString,
+    syntax::package::{PackageSpec, PackageVersion},
+};
 
 #[derive(Serialize)]
 pub struct PackageSpecSer {
@@ -18,3 +23,29 @@ impl From<&PackageSpec> for PackageSpecSer {
         }
     }
 }
+
+#[derive(Deserialize)]
+pub struct PackageIndexEntrySer {
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+}
+
+fn default_namespace() -> String {
+    "preview".into()
+}
+
+impl PackageIndexEntrySer {
+    pub fn to_spec(&self) -> Option<(PackageSpec, Option<EcoString>)> {
+        let version = PackageVersion::from_str(&self.version).ok()?;
+        let spec = PackageSpec {
+            namespace: self.namespace.as_str().into(),
+            name: self.name.as_str().into(),
+            version,
+        };
+
+        Some((spec, self.description.as_deref().map(Into::into)))
+    }
+}
@@ -0,0 +1,182 @@
+use std::ops::Range;
+
+use serde::Serialize;
+
+use typst::{
+    World,
+    ecow::EcoString,
+    syntax::{
+        FileId, LinkedNode, Side, Source, Span, SyntaxKind,
+        ast::{self, AstNode},
+    },
+};
+use typst_ide::Definition;
+
+use crate::world::WasmWorld;
+
+pub enum Target {
+    Ident { name: EcoString, span: Span },
+    Label(EcoString),
+}
+
+pub struct Occurrence {
+    pub id: FileId,
+    pub range: Range<usize>,
+    pub definition: bool,
+}
+
+#[derive(Serialize)]
+pub struct ReferenceSer {
+    pub package: Option<String>,
+    pub path: String,
+    pub from: usize,
+    pub to: usize,
+    pub definition: bool,
+}
+
+impl ReferenceSer {
+    pub fn from_occurrence(occurrence: &Occurrence, world: &WasmWorld) -> Option<Self> {
+        let source = world.source(occurrence.id).ok()?;
+        let lines = source.lines();
+
+        Some(ReferenceSer {
+            package: occurrence.id.package().map(|spec| spec.to_string()),
+            path: occurrence
+                .id
+                .vpath()
+                .as_rootless_path()
+                .to_string_lossy()
+                .to_string(),
+            from: lines.byte_to_utf16(occurrence.range.start)?,
+            to: lines.byte_to_utf16(occurrence.range.end)?,
+            definition: occurrence.definition,
+        })
+    }
+}
+
+pub fn find_target(world: &WasmWorld, source: &Source, cursor: usize) -> Option<Target> {
+    let root = LinkedNode::new(source.root());
+    let leaf = [Side::After, Side::Before]
+        .into_iter()
+        .filter_map(|side| root.leaf_at(cursor, side))
+        .find(|leaf| {
+            matches!(
+                leaf.kind(),
+                SyntaxKind::Ident
+                    | SyntaxKind::MathIdent
+                    | SyntaxKind::Label
+                    | SyntaxKind::RefMarker
+            )
+        })?;
+
+    match leaf.kind() {
+        SyntaxKind::Label => Some(Target::Label(leaf.cast::<ast::Label>()?.get().into())),
+        SyntaxKind::RefMarker => {
+            let reference = leaf.parent()?.cast::<ast::Ref>()?;
+            Some(Target::Label(reference.target().into()))
+        }
+        _ => Some(Target::Ident {
+            name: leaf.text().clone(),
+            span: resolve_definition(world, source, &leaf)?,
+        }),
+    }
+}
+
+pub fn find_occurrences(world: &WasmWorld, target: &Target) -> Vec<Occurrence> {
+    let mut occurrences = Vec::new();
+    for source in world.local_sources() {
+        collect(
+            world,
+            &source,
+            &LinkedNode::new(source.root()),
+            target,
+            &mut occurrences,
+        );
+    }
+
+    occurrences
+}
+
+fn collect(
+    world: &WasmWorld,
+    source: &Source,
+    node: &LinkedNode,
+    target: &Target,
+    out: &mut Vec<Occurrence>,
+) {
+    match (node.kind(), target) {
+        (SyntaxKind::Ident | SyntaxKind::MathIdent, Target::Ident { name, span })
+            if node.text() == name =>
+        {
+            if resolve_definition(world, source, node) == Some(*span) {
+                out.push(Occurrence {
+                    id: source.id(),
+                    range: node.range(),
+                    definition: node.span() == *span,
+                });
+            }
+        }
+        (SyntaxKind::Label, Target::Label(name)) => {
+            if let Some(label) = node.cast::<ast::Label>()
+                && label.get() == name.as_str()
+            {
+                // ? < > を除く
+                let range = node.range();
+                out.push(Occurrence {
+                    id: source.id(),
+                    range: range.start + 1..range.end - 1,
+                    definition: true,
+                });
+            }
+        }
+        (SyntaxKind::Ref, Target::Label(name)) => {
+            if let Some(reference) = node.cast::<ast::Ref>()
+                && reference.target() == name.as_str()
+            {
+                // ? @ を除く
+                let start = node.offset() + 1;
+                out.push(Occurrence {
+                    id: source.id(),
+                    range: start..start + name.len(),
+                    definition: false,
+                });
+            }
+        }
+        _ => {}
+    }
+
+    for child in node.children() {
+        collect(world, source, &child, target, out);
+    }
+}
+
+// ? 定義が見つからない識別子は，それ自身を定義とみなす
+fn resolve_definition(world: &WasmWorld, source: &Source, leaf: &LinkedNode) -> Option<Span> {
+    match typst_ide::definition(world, None, source, leaf.offset(), Side::After) {
+        Some(Definition::Span(span)) => Some(binding_ident(world, span)),
+        Some(Definition::Std(_)) => None,
+        None => Some(leaf.span()),
+    }
+}
+
+// ? クロージャの定義は名前の識別子に揃える
+fn binding_ident(world: &WasmWorld, span: Span) -> Span {
+    let Some(id) = span.id() else {
+        return span;
+    };
+    let Ok(source) = world.source(id) else {
+        return span;
+    };
+    let root = LinkedNode::new(source.root());
+    let Some(node) = root.find(span) else {
+        return span;
+    };
+
+    match node
+        .cast::<ast::Closure>()
+        .and_then(|closure| closure.name())
+    {
+        Some(ident) => ident.span(),
+        None => span,
+    }
+}
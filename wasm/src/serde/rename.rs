@@ -0,0 +1,68 @@
+use serde::Serialize;
+
+use typst::syntax::{is_id_continue, is_ident};
+
+use crate::serde::reference::{self, Target};
+use crate::world::WasmWorld;
+
+const KEYWORDS: &[&str] = &[
+    "none", "auto", "true", "false", "not", "and", "or", "let", "set", "show", "context", "if",
+    "else", "for", "in", "while", "break", "continue", "return", "import", "include", "as",
+];
+
+#[derive(Serialize)]
+pub struct TextEditSer {
+    pub path: String,
+    pub from: usize,
+    pub to: usize,
+    pub replacement: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum RenameErrorSer {
+    NoTarget,
+    InvalidName { name: String },
+    Package { package: String },
+}
+
+pub fn rename(
+    world: &WasmWorld,
+    target: &Target,
+    new_name: &str,
+) -> Result<Vec<TextEditSer>, RenameErrorSer> {
+    let valid = match target {
+        Target::Ident { span, .. } => {
+            if let Some(spec) = span.id().and_then(|id| id.package().cloned()) {
+                return Err(RenameErrorSer::Package {
+                    package: spec.to_string(),
+                });
+            }
+            is_ident(new_name) && !KEYWORDS.contains(&new_name)
+        }
+        Target::Label(_) => {
+            !new_name.is_empty()
+                && new_name
+                    .chars()
+                    .all(|c| is_id_continue(c) || c == ':' || c == '.')
+        }
+    };
+    if !valid {
+        return Err(RenameErrorSer::InvalidName {
+            name: new_name.to_string(),
+        });
+    }
+
+    let edits = reference::find_occurrences(world, target)
+        .iter()
+        .filter_map(|o| reference::ReferenceSer::from_occurrence(o, world))
+        .map(|r| TextEditSer {
+            path: r.path,
+            from: r.from,
+            to: r.to,
+            replacement: new_name.to_string(),
+        })
+        .collect();
+
+    Ok(edits)
+}
@@ -0,0 +1,16 @@
+use serde::Serialize;
+
+use crate::semantic;
+
+#[derive(Serialize)]
+pub struct SemanticLegendSer {
+    pub types: Vec<String>,
+    pub modifiers: Vec<String>,
+}
+
+pub fn legend() -> SemanticLegendSer {
+    SemanticLegendSer {
+        types: semantic::token_types(),
+        modifiers: semantic::token_modifiers(),
+    }
+}
@@ -0,0 +1,143 @@
+use serde::Serialize;
+
+use typst::{
+    World,
+    foundations::{CastInfo, Func, ParamInfo, Repr},
+    syntax::{
+        LinkedNode,
+        ast::{self, AstNode},
+    },
+};
+
+#[derive(Serialize)]
+pub struct ParamSer {
+    pub name: String,
+    pub types: Vec<String>,
+    pub default: Option<String>,
+    pub docs: Option<String>,
+    pub positional: bool,
+    pub named: bool,
+    pub variadic: bool,
+    pub required: bool,
+}
+
+#[derive(Serialize)]
+pub struct SignatureSer {
+    pub name: Option<String>,
+    pub docs: Option<String>,
+    pub params: Vec<ParamSer>,
+    pub active: Option<usize>,
+}
+
+impl From<&ParamInfo> for ParamSer {
+    fn from(param: &ParamInfo) -> Self {
+        ParamSer {
+            name: param.name.to_string(),
+            types: cast_info_names(&param.input),
+            default: param.default.map(|default| default().repr().to_string()),
+            docs: Some(param.docs.to_string()).filter(|docs| !docs.is_empty()),
+            positional: param.positional,
+            named: param.named,
+            variadic: param.variadic,
+            required: param.required,
+        }
+    }
+}
+
+impl From<ast::Param<'_>> for ParamSer {
+    fn from(param: ast::Param) -> Self {
+        let (name, default, positional, named, variadic) = match param {
+            ast::Param::Pos(pattern) => (node_text(pattern), None, true, false, false),
+            ast::Param::Named(named) => (
+                named.name().as_str().to_string(),
+                Some(node_text(named.expr())),
+                false,
+                true,
+                false,
+            ),
+            ast::Param::Spread(spread) => {
+                let text = node_text(spread);
+                let name = text.trim_start_matches("..").to_string();
+                (name, None, true, false, true)
+            }
+        };
+
+        ParamSer {
+            name,
+            types: Vec::new(),
+            required: positional && !variadic,
+            default,
+            docs: None,
+            positional,
+            named,
+            variadic,
+        }
+    }
+}
+
+impl SignatureSer {
+    pub fn from_func<W>(func: &Func, world: &W) -> Option<Self>
+    where
+        W: World,
+    {
+        let params = match func.params() {
+            Some(params) => params.iter().map(Into::into).collect(),
+            // ? クロージャはパラメータ情報を持たないため，定義を構文木から読む
+            None => {
+                let id = func.span().id()?;
+                let source = world.source(id).ok()?;
+                let node = LinkedNode::new(source.root()).find(func.span())?;
+                let closure = node.cast::<ast::Closure>()?;
+
+                closure.params().children().map(Into::into).collect()
+            }
+        };
+
+        Some(SignatureSer {
+            name: func.name().map(Into::into),
+            docs: func.docs().map(Into::into),
+            params,
+            active: None,
+        })
+    }
+
+    pub fn activate(&mut self, positional: usize, named: Option<&str>) {
+        self.active = match named {
+            Some(name) => self.params.iter().position(|p| p.named && p.name == name),
+            None => {
+                let indices: Vec<usize> = self
+                    .params
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, p)| p.positional)
+                    .map(|(i, _)| i)
+                    .collect();
+
+                // ? 可変長引数は残りの位置引数をすべて受け取る
+                indices
+                    .get(positional)
+                    .copied()
+                    .or_else(|| indices.last().copied().filter(|&i| self.params[i].variadic))
+            }
+        };
+    }
+}
+
+fn cast_info_names(info: &CastInfo) -> Vec<String> {
+    let mut names = Vec::new();
+    info.walk(|info| match info {
+        CastInfo::Any => names.push("any".to_string()),
+        CastInfo::Value(value, _) => names.push(value.repr().to_string()),
+        CastInfo::Type(ty) => names.push(ty.short_name().to_string()),
+        CastInfo::Union(_) => {}
+    });
+    names.dedup();
+    names
+}
+
+fn node_text<'a, N>(node: N) -> String
+where
+    N: AstNode<'a>,
+{
+    node.to_untyped().clone().into_text().to_string()
+}
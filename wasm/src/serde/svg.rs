@@ -5,22 +5,33 @@ use wasm_bindgen::JsValue;
 
 use typst::{diag::SourceDiagnostic, ecow};
 
+use crate::inline::Metrics;
 use crate::serde::diagnostic::SourceDiagnosticSer;
 use crate::world::WasmWorld;
 
 #[derive(Serialize)]
 struct SvgResultSer {
     svg: String,
+    baseline: f64,
+    height: f64,
+    depth: f64,
+    pages: Vec<String>,
     diags: Vec<SourceDiagnosticSer>,
 }
 
 pub fn svg(
     svg: String,
+    pages: Vec<String>,
+    metrics: Metrics,
     diags: EcoVec<SourceDiagnostic>,
     world: &WasmWorld,
 ) -> Result<JsValue, JsValue> {
     let result = SvgResultSer {
         svg,
+        baseline: metrics.baseline,
+        height: metrics.height,
+        depth: metrics.depth,
+        pages,
         diags: diags
             .iter()
             .map(|d| SourceDiagnosticSer::from_diag(d, world))
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use serde_wasm_bindgen::to_value;
+use wasm_bindgen::JsValue;
+
+use crate::budget::Interrupted;
+use crate::cache::CachedSvg;
+use crate::inline::{InlineError, InlineOutput};
+use crate::serde::diagnostic::SourceDiagnosticSer;
+use crate::serde::options::AltOptionsSer;
+use crate::world::WasmWorld;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SvgBatchItemSer {
+    pub code: String,
+    pub ndir: String,
+    pub kind: String,
+    pub id: String,
+    #[serde(default)]
+    pub alt: Option<AltOptionsSer>,
+    pub id_prefix: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SvgBatchOptionsSer {
+    // ? グリフ定義を 1 つの SVG にまとめる
+    #[serde(default)]
+    pub shared_defs: bool,
+}
+
+#[derive(Serialize)]
+struct SvgBatchSer {
+    items: Vec<SvgBatchResultSer>,
+    defs: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+enum SvgBatchResultSer {
+    Ok {
+        svg: String,
+        baseline: f64,
+        height: f64,
+        depth: f64,
+        pages: Vec<String>,
+        diags: Vec<SourceDiagnosticSer>,
+    },
+    Error {
+        message: Option<String>,
+        interrupted: Option<Interrupted>,
+        diags: Vec<SourceDiagnosticSer>,
+    },
+}
+
+// ? 1 項目のコンパイル結果と，その診断
+pub struct BatchOutput {
+    pub result: Result<InlineOutput, InlineError>,
+    pub diags: Vec<SourceDiagnosticSer>,
+}
+
+impl BatchOutput {
+    // ? 診断はコンパイル直後に変換する (後の項目のコンパイルで LRU からファイルが追い出されるため)
+    pub fn new(result: Result<InlineOutput, InlineError>, world: &WasmWorld) -> Self {
+        let diags = match &result {
+            Ok(output) => &output.warnings,
+            Err(InlineError::Diags(errs)) => errs,
+            Err(_) => {
+                return Self {
+                    result,
+                    diags: Vec::new(),
+                };
+            }
+        };
+        let diags = diags
+            .iter()
+            .map(|d| SourceDiagnosticSer::from_diag(d, world))
+            .collect();
+
+        Self { result, diags }
+    }
+}
+
+pub fn svg_batch(
+    outputs: Vec<Option<BatchOutput>>,
+    svgs: Vec<Option<CachedSvg>>,
+    defs: Option<String>,
+) -> Result<JsValue, JsValue> {
+    let items: Vec<SvgBatchResultSer> = outputs
+        .into_iter()
+        .zip(svgs)
+        .map(|(output, svg)| match (output, svg) {
+            // ? 結果キャッシュから
+            (None, Some(cached)) => SvgBatchResultSer::Ok {
+                svg: cached.svg,
+                baseline: cached.metrics.baseline,
+                height: cached.metrics.height,
+                depth: cached.metrics.depth,
+                pages: cached.pages,
+                diags: Vec::new(),
+            },
+            (
+                Some(BatchOutput {
+                    result: Ok(_),
+                    diags,
+                }),
+                Some(cached),
+            ) => SvgBatchResultSer::Ok {
+                svg: cached.svg,
+                baseline: cached.metrics.baseline,
+                height: cached.metrics.height,
+                depth: cached.metrics.depth,
+                pages: cached.pages,
+                diags,
+            },
+            (
+                Some(BatchOutput {
+                    result: Err(InlineError::Interrupted(reason)),
+                    ..
+                }),
+                _,
+            ) => SvgBatchResultSer::Error {
+                message: Some(reason.message().to_string()),
+                interrupted: Some(reason),
+                diags: Vec::new(),
+            },
+            (
+                Some(BatchOutput {
+                    result: Err(InlineError::Diags(_)),
+                    diags,
+                }),
+                _,
+            ) => SvgBatchResultSer::Error {
+                message: None,
+                interrupted: None,
+                diags,
+            },
+            _ => SvgBatchResultSer::Error {
+                message: Some("document has no pages".to_string()),
+                interrupted: None,
+                diags: Vec::new(),
+            },
+        })
+        .collect();
+
+    Ok(to_value(&SvgBatchSer { items, defs })?)
+}
@@ -0,0 +1,23 @@
+use serde::Serialize;
+
+use typst_ide::Tooltip;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum TooltipSer {
+    Text { text: String },
+    Code { code: String },
+}
+
+impl From<&Tooltip> for TooltipSer {
+    fn from(tooltip: &Tooltip) -> Self {
+        match tooltip {
+            Tooltip::Text(text) => TooltipSer::Text {
+                text: text.to_string(),
+            },
+            Tooltip::Code(code) => TooltipSer::Code {
+                code: code.to_string(),
+            },
+        }
+    }
+}
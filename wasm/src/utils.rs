@@ -1,5 +1,9 @@
 use std::num::NonZeroUsize;
-use typst::layout::{Abs, Frame, FrameItem, PageRanges};
+use typst::foundations::Value;
+use typst::introspection::Tag;
+use typst::layout::{Abs, Frame, FrameItem, Page, PageRanges, Point, Size, Transform};
+use typst::math::EquationElem;
+use typst::syntax::{LinkedNode, Side, SyntaxKind, ast};
 
 pub fn parse_page_ranges(s: &str) -> Option<PageRanges> {
     let mut ranges = Vec::new();
@@ -37,20 +41,422 @@ pub fn parse_page_ranges(s: &str) -> Option<PageRanges> {
     Some(PageRanges::new(ranges))
 }
 
+// ? 最初のインライン数式の開始タグの位置 (タグは行のベースライン上に置かれる)，なければ目印のテキストの位置
 pub fn find_baseline(frame: &Frame, offset_y: Abs) -> Option<Abs> {
+    equation_baseline(frame, offset_y).or_else(|| marker_baseline(frame, offset_y))
+}
+
+// ? 行やグループのフレームは親に統合されてベースラインが失われるため，タグで数式の位置を求める
+fn equation_baseline(frame: &Frame, offset_y: Abs) -> Option<Abs> {
+    for (pos, item) in frame.items() {
+        match item {
+            FrameItem::Tag(Tag::Start(elem, ..))
+                if elem.is::<EquationElem>()
+                    && elem.get_by_name("block").ok() != Some(Value::Bool(true)) =>
+            {
+                return Some(offset_y + pos.y);
+            }
+            FrameItem::Group(group) => {
+                if let Some(baseline) = equation_baseline(&group.frame, offset_y + pos.y) {
+                    return Some(baseline);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn marker_baseline(frame: &Frame, offset_y: Abs) -> Option<Abs> {
     let mut stack: Vec<(&Frame, Abs)> = Vec::with_capacity(16);
     stack.push((frame, offset_y));
 
     while let Some((cur, cur_offset)) = stack.pop() {
         for (pos, item) in cur.items().rev() {
-            if let FrameItem::Text(text) = item {
-                if text.text.as_bytes() == b"TypstMate" {
+            match item {
+                FrameItem::Text(text) if text.text.as_bytes() == b"TypstMate" => {
                     return Some(cur_offset + pos.y);
                 }
-            } else if let FrameItem::Group(group) = item {
-                stack.push((&group.frame, cur_offset + pos.y));
+                FrameItem::Group(group) => stack.push((&group.frame, cur_offset + pos.y)),
+                _ => {}
             }
         }
     }
     None
 }
+
+// ? 表示される要素 (テキスト，図形，画像) の外接矩形
+pub fn visible_bounds(frame: &Frame) -> Option<(Point, Point)> {
+    let mut min = Point::splat(Abs::inf());
+    let mut max = Point::splat(-Abs::inf());
+    let mut stack: Vec<(&Frame, Transform)> = Vec::with_capacity(16);
+    stack.push((frame, Transform::identity()));
+
+    while let Some((cur, ts)) = stack.pop() {
+        for (pos, item) in cur.items() {
+            let ts = ts.pre_concat(Transform::translate(pos.x, pos.y));
+            let (from, to) = match item {
+                FrameItem::Group(group) => {
+                    stack.push((&group.frame, ts.pre_concat(group.transform)));
+                    continue;
+                }
+                FrameItem::Text(text) => {
+                    let metrics = text.font.metrics();
+                    (
+                        Point::new(Abs::zero(), -metrics.ascender.at(text.size)),
+                        Point::new(text.width(), -metrics.descender.at(text.size)),
+                    )
+                }
+                FrameItem::Shape(shape, _) => {
+                    let size = shape.geometry.bbox_size();
+                    let half = shape
+                        .stroke
+                        .as_ref()
+                        .map(|stroke| stroke.thickness / 2.0)
+                        .unwrap_or_default();
+                    (
+                        Point::splat(-half),
+                        Point::new(size.x + half, size.y + half),
+                    )
+                }
+                FrameItem::Image(_, size, _) => (Point::zero(), size.to_point()),
+                _ => continue,
+            };
+
+            for corner in [from, Point::new(to.x, from.y), Point::new(from.x, to.y), to] {
+                let p = corner.transform(ts);
+                min = Point::new(min.x.min(p.x), min.y.min(p.y));
+                max = Point::new(max.x.max(p.x), max.y.max(p.y));
+            }
+        }
+    }
+
+    (min.x <= max.x && min.y <= max.y).then_some((min, max))
+}
+
+// ? 表示される要素だけを含むようにページを切り詰める
+pub fn crop_page(page: &Page, padding: Abs) -> Page {
+    let Some((min, max)) = visible_bounds(&page.frame) else {
+        return page.clone();
+    };
+
+    let size = Size::new(max.x - min.x, max.y - min.y) + Size::splat(padding * 2.0);
+    let mut frame = Frame::hard(size);
+    frame.push_frame(
+        Point::new(padding - min.x, padding - min.y),
+        page.frame.clone(),
+    );
+
+    let mut cropped = page.clone();
+    cropped.frame = frame;
+    cropped
+}
+
+// ? 全ページを縦に並べて 1 ページにする
+pub fn stitch_pages(pages: &[Page]) -> Option<Page> {
+    let mut stitched = pages.first()?.clone();
+    let width = pages
+        .iter()
+        .map(|page| page.frame.width())
+        .fold(Abs::zero(), Abs::max);
+    let height = pages
+        .iter()
+        .map(|page| page.frame.height())
+        .fold(Abs::zero(), |a, b| a + b);
+
+    let mut frame = Frame::hard(Size::new(width, height));
+    let mut y = Abs::zero();
+    for page in pages {
+        frame.push_frame(Point::with_y(y), page.frame.clone());
+        y += page.frame.height();
+    }
+
+    stitched.frame = frame;
+    Some(stitched)
+}
+
+// ? id="..", href="#..", url(#..) の id に接頭辞を付ける
+pub fn prefix_ids<'a>(svg: &str, prefix: impl Fn(&str) -> &'a str) -> String {
+    const PATTERNS: [&str; 3] = ["id=\"", "href=\"#", "url(#"];
+
+    let mut out = String::with_capacity(svg.len() + svg.len() / 8);
+    let mut rest = svg;
+    while let Some((start, pattern)) = PATTERNS
+        .iter()
+        .filter_map(|p| rest.find(p).map(|i| (i, *p)))
+        .min_by_key(|(i, _)| *i)
+    {
+        let id_start = start + pattern.len();
+        out.push_str(&rest[..id_start]);
+        rest = &rest[id_start..];
+
+        let id_end = rest.find(['"', ')']).unwrap_or(rest.len());
+        out.push_str(prefix(&rest[..id_end]));
+        out.push_str(&rest[..id_end]);
+        rest = &rest[id_end..];
+    }
+    out.push_str(rest);
+
+    out
+}
+
+// ? <defs id="glyph"> を取り除き，その中の要素を (id, 要素) として返す
+pub fn extract_glyph_defs(svg: &str) -> (String, Vec<(String, String)>) {
+    const OPEN: &str = "<defs id=\"glyph\">";
+    const CLOSE: &str = "</defs>";
+
+    let Some(start) = svg.find(OPEN) else {
+        return (svg.to_string(), Vec::new());
+    };
+    let Some(end) = svg[start..].find(CLOSE).map(|i| start + i) else {
+        return (svg.to_string(), Vec::new());
+    };
+
+    let mut glyphs = Vec::new();
+    let mut rest = &svg[start + OPEN.len()..end];
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open..];
+        let name_end = rest.find([' ', '>', '/']).unwrap_or(rest.len());
+        let name = &rest[1..name_end];
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let len = if rest[..tag_end].ends_with('/') {
+            tag_end + 1
+        } else {
+            let close = format!("</{}>", name);
+            match rest.find(&close) {
+                Some(i) => i + close.len(),
+                None => break,
+            }
+        };
+
+        let element = &rest[..len];
+        if let Some(id) = element
+            .split_once("id=\"")
+            .and_then(|(_, after)| after.split_once('"'))
+            .map(|(id, _)| id)
+        {
+            glyphs.push((id.to_string(), element.to_string()));
+        }
+        rest = &rest[len..];
+    }
+
+    let stripped = format!("{}{}", &svg[..start], &svg[end + CLOSE.len()..]);
+    (stripped, glyphs)
+}
+
+pub struct CallContext<'a> {
+    pub callee: LinkedNode<'a>,
+    pub positional: usize,
+    pub named: Option<String>,
+}
+
+// ? カーソルを括弧内に含む最も内側の関数呼び出しを探す
+pub fn find_call<'a>(root: &'a LinkedNode<'a>, cursor: usize) -> Option<CallContext<'a>> {
+    let mut node = root.leaf_at(cursor, Side::Before)?;
+
+    loop {
+        if node.kind() == SyntaxKind::Args
+            && let Some(call) = node.parent()
+            && call.kind() == SyntaxKind::FuncCall
+            && inside_parens(&node, cursor)
+        {
+            let callee = call.children().next()?;
+            let (positional, named) = active_arg(&node, cursor);
+            return Some(CallContext {
+                callee,
+                positional,
+                named,
+            });
+        }
+        node = node.parent()?.clone();
+    }
+}
+
+fn inside_parens(args: &LinkedNode, cursor: usize) -> bool {
+    let mut children = args.children();
+    let Some(open) = children.find(|c| c.kind() == SyntaxKind::LeftParen) else {
+        return false;
+    };
+    let close = children.find(|c| c.kind() == SyntaxKind::RightParen);
+
+    open.range().end <= cursor && close.is_none_or(|c| cursor <= c.offset())
+}
+
+fn active_arg(args: &LinkedNode, cursor: usize) -> (usize, Option<String>) {
+    let mut positional = 0;
+    let mut named = None;
+    let mut has_positional = false;
+
+    for child in args.children() {
+        if child.offset() >= cursor {
+            break;
+        }
+
+        match child.kind() {
+            SyntaxKind::Comma => {
+                if has_positional {
+                    positional += 1;
+                }
+                has_positional = false;
+                named = None;
+            }
+            SyntaxKind::Named => {
+                named = child
+                    .cast::<ast::Named>()
+                    .map(|n| n.name().as_str().to_string());
+            }
+            SyntaxKind::LeftParen | SyntaxKind::RightParen | SyntaxKind::Spread => {}
+            kind if kind.is_trivia() => {}
+            _ => has_positional = true,
+        }
+    }
+
+    (positional, named)
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::diag::{FileError, FileResult};
+    use typst::foundations::{Bytes, Datetime};
+    use typst::layout::PagedDocument;
+    use typst::syntax::{FileId, Source};
+    use typst::text::{Font, FontBook};
+    use typst::utils::LazyHash;
+    use typst::{Library, LibraryExt, World};
+
+    use super::*;
+
+    struct TestWorld {
+        library: LazyHash<Library>,
+        book: LazyHash<FontBook>,
+        fonts: Vec<Font>,
+        source: Source,
+    }
+
+    impl TestWorld {
+        fn new(text: &str) -> Self {
+            let fonts: Vec<Font> = typst_assets::fonts()
+                .flat_map(|data| Font::iter(Bytes::new(data)))
+                .collect();
+            Self {
+                library: LazyHash::new(Library::default()),
+                book: LazyHash::new(FontBook::from_fonts(&fonts)),
+                fonts,
+                source: Source::detached(text),
+            }
+        }
+    }
+
+    impl World for TestWorld {
+        fn library(&self) -> &LazyHash<Library> {
+            &self.library
+        }
+
+        fn book(&self) -> &LazyHash<FontBook> {
+            &self.book
+        }
+
+        fn main(&self) -> FileId {
+            self.source.id()
+        }
+
+        fn source(&self, id: FileId) -> FileResult<Source> {
+            if id == self.source.id() {
+                Ok(self.source.clone())
+            } else {
+                Err(FileError::NotFound(id.vpath().as_rootless_path().into()))
+            }
+        }
+
+        fn file(&self, id: FileId) -> FileResult<Bytes> {
+            Err(FileError::NotFound(id.vpath().as_rootless_path().into()))
+        }
+
+        fn font(&self, index: usize) -> Option<Font> {
+            self.fonts.get(index).cloned()
+        }
+
+        fn today(&self, _: Option<i64>) -> Option<Datetime> {
+            None
+        }
+    }
+
+    // ? インライン数式のプロセッサと同じ形でコンパイルし，ページのフレームを返す
+    fn layout(code: &str) -> Frame {
+        let world = TestWorld::new(&format!(
+            "#set page(width: auto, height: auto, margin: 0pt)\n${}$",
+            code
+        ));
+        let mut document = typst::compile::<PagedDocument>(&world).output.ok().unwrap();
+        document.pages.remove(0).frame
+    }
+
+    // ? (baseline, depth)
+    fn metrics(frame: &Frame) -> (f64, f64) {
+        let baseline = find_baseline(frame, Abs::zero()).unwrap().to_pt();
+        (baseline, frame.height().to_pt() - baseline)
+    }
+
+    // ? 条件に合うテキストのベースラインの位置 (フレーム順)
+    fn text_baselines(frame: &Frame, accept: impl Fn(&str) -> bool) -> Vec<f64> {
+        fn walk(frame: &Frame, offset_y: Abs, accept: &dyn Fn(&str) -> bool, out: &mut Vec<f64>) {
+            for (pos, item) in frame.items() {
+                match item {
+                    FrameItem::Group(group) => walk(&group.frame, offset_y + pos.y, accept, out),
+                    FrameItem::Text(text) if accept(&text.text) => {
+                        out.push((offset_y + pos.y).to_pt())
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        walk(frame, Abs::zero(), &accept, &mut out);
+        out
+    }
+
+    #[test]
+    fn baseline_of_fraction() {
+        // ? 分子のベースラインではなく，分子と分母の間にある
+        let frame = layout("a/b");
+        let (baseline, depth) = metrics(&frame);
+        let texts = text_baselines(&frame, |_| true);
+        assert_eq!(texts.len(), 2);
+
+        let (numerator, denominator) = (texts[0].min(texts[1]), texts[0].max(texts[1]));
+        assert!(numerator + 0.01 < baseline, "{numerator} / {baseline}");
+        assert!(baseline + 0.01 < denominator, "{baseline} / {denominator}");
+        assert!(depth > 0.0);
+    }
+
+    #[test]
+    fn baseline_of_root() {
+        // ? 根号の記号ではなく，中身のベースライン
+        let frame = layout("sqrt(x)");
+        let (baseline, depth) = metrics(&frame);
+        let radicand = text_baselines(&frame, |text| !text.contains('√'));
+        assert_eq!(radicand.len(), 1);
+
+        assert!(
+            (baseline - radicand[0]).abs() < 0.01,
+            "{baseline} / {radicand:?}"
+        );
+        assert!(depth > 0.0);
+    }
+
+    #[test]
+    fn baseline_of_attach() {
+        // ? 上付きは上に伸びるだけで，深さは文字単体と変わらない
+        let frame = layout("x^2");
+        let (baseline, depth) = metrics(&frame);
+        let base = text_baselines(&frame, |text| text != "2");
+        assert_eq!(base.len(), 1);
+        assert!((baseline - base[0]).abs() < 0.01, "{baseline} / {base:?}");
+
+        let (_, x_depth) = metrics(&layout("x"));
+        assert!((depth - x_depth).abs() < 0.01, "{depth} / {x_depth}");
+    }
+}
@@ -54,9 +54,28 @@ impl FileSlot {
         let mut source_mut = self.source.borrow_mut();
         if let Ok(source) = &mut *source_mut {
             source.replace(new);
+            // ? 依存関係のハッシュは bytes から求めるため，edit と同様に更新する
+            *self.bytes.borrow_mut() = Ok(Bytes::from_string(new.to_string()));
         }
     }
 
+    // ? from, to は UTF-16 オフセット
+    pub fn edit(&self, from: usize, to: usize, with: &str) -> Option<()> {
+        let mut source_mut = self.source.borrow_mut();
+        let source = source_mut.as_mut().ok()?;
+
+        let lines = source.lines();
+        let from = lines.utf16_to_byte(from)?;
+        let to = lines.utf16_to_byte(to)?;
+        if to < from {
+            return None;
+        }
+        source.edit(from..to, with);
+
+        *self.bytes.borrow_mut() = Ok(Bytes::from_string(source.text().to_string()));
+        Some(())
+    }
+
     pub fn source(&self) -> FileResult<Source> {
         self.source.borrow().clone()
     }
@@ -15,14 +15,17 @@ use typst::layout::{Abs, BoxElem, Em, Length, Rel, Sides};
 use typst::syntax::{FileId, Source, Span, VirtualPath, package::PackageSpec};
 use typst::text::{Font, FontBook, FontList, SmallcapsElem, TextElem};
 use typst::{
-    Library, LibraryExt, World,
+    Feature, Library, LibraryExt, World,
     diag::{FileError, FileResult, PackageError},
+    ecow::EcoString,
     utils::LazyHash,
     visualize::{Color, Paint, Stroke},
 };
 
 use typst_ide::IdeWorld;
 
+use crate::budget::Budget;
+use crate::cache;
 use crate::vfs::FileSlot;
 
 pub struct WasmWorld {
@@ -35,6 +38,17 @@ pub struct WasmWorld {
 
     read: SendWrapper<js_sys::Function>,
     packages: FxHashSet<PackageSpec>,
+
+    // ? IdeWorld 用
+    vault_files: FxHashSet<FileId>,
+    // ? rename / references 用．Vault に実在するファイル (インライン数式などの仮想ファイルを除く)
+    local_files: Mutex<FxHashSet<FileId>>,
+    package_index: FxHashMap<PackageSpec, Option<EcoString>>,
+    package_list: Vec<(PackageSpec, Option<EcoString>)>,
+
+    budget: Budget,
+    // ? 結果キャッシュの依存関係のため，読み込まれたローカルファイルを記録する
+    accessed: Mutex<FxHashSet<FileId>>,
 }
 
 impl WasmWorld {
@@ -55,7 +69,10 @@ impl WasmWorld {
         }
 
         // ライブラリを設定
-        let mut library = Library::default();
+        // ? htmlr のため HTML 出力を有効化
+        let mut library = Library::builder()
+            .with_features([Feature::Html].into_iter().collect())
+            .build();
 
         // ライブラリのグローバル・数学定義
         // #let fontsize = (16 / 1.25) * 1pt
@@ -124,6 +141,14 @@ impl WasmWorld {
 
             read: SendWrapper::new(read),
             packages: FxHashSet::default(),
+
+            vault_files: FxHashSet::default(),
+            local_files: Mutex::new(FxHashSet::default()),
+            package_index: FxHashMap::default(),
+            package_list: Vec::new(),
+
+            budget: Budget::default(),
+            accessed: Mutex::new(FxHashSet::default()),
         }
     }
 
@@ -138,6 +163,13 @@ impl WasmWorld {
         m.get_mut(&self.main).unwrap().replace(new);
     }
 
+    pub fn edit(&self, vpath: VirtualPath, from: usize, to: usize, with: &str) -> Option<()> {
+        let m = self.slots.lock().unwrap();
+        let file_id = FileId::new(None, vpath);
+
+        m.get(&file_id)?.edit(from, to, with)
+    }
+
     pub fn add_file_text(&self, vpath: VirtualPath, text: String) {
         let mut m = self.slots.lock().unwrap();
         let file_id = FileId::new(None, vpath);
@@ -152,18 +184,84 @@ impl WasmWorld {
         m.insert(file_id, FileSlot::new_from_bytes(file_id, bytes));
     }
 
+    pub fn remove_file(&self, id: FileId) {
+        let mut m = self.slots.lock().unwrap();
+
+        m.remove(&id);
+        self.local_files.lock().unwrap().remove(&id);
+    }
+
+    // ? store の files やノートなど，Vault に実在するファイルとして扱う
+    pub fn mark_local(&self, id: FileId) {
+        self.local_files.lock().unwrap().insert(id);
+    }
+
     pub fn add_package_file(&mut self, spec: PackageSpec, vpath: &str, bytes: Vec<u8>) {
         let mut m = self.slots.lock().unwrap();
         let file_id = FileId::new(Some(spec.clone()), VirtualPath::new(vpath));
         m.insert(file_id, FileSlot::new_from_bytes(file_id, bytes));
 
-        self.packages.insert(spec);
+        if self.packages.insert(spec) {
+            self.update_package_list();
+        }
     }
 
     pub fn list_packages(&self) -> Vec<PackageSpec> {
         self.packages.iter().cloned().collect()
     }
 
+    pub fn set_vault_files(&mut self, vpaths: Vec<VirtualPath>) {
+        self.vault_files = vpaths
+            .into_iter()
+            .map(|vpath| FileId::new(None, vpath))
+            .collect();
+    }
+
+    pub fn set_package_index(&mut self, index: Vec<(PackageSpec, Option<EcoString>)>) {
+        self.package_index = index.into_iter().collect();
+        self.update_package_list();
+    }
+
+    fn update_package_list(&mut self) {
+        let mut list: Vec<(PackageSpec, Option<EcoString>)> = self
+            .package_index
+            .iter()
+            .map(|(spec, description)| (spec.clone(), description.clone()))
+            .collect();
+        list.extend(
+            self.packages
+                .iter()
+                .filter(|spec| !self.package_index.contains_key(*spec))
+                .map(|spec| (spec.clone(), None)),
+        );
+        list.sort_by(|(a, _), (b, _)| {
+            (&a.namespace, &a.name, b.version).cmp(&(&b.namespace, &b.name, a.version))
+        });
+
+        self.package_list = list;
+    }
+
+    // ? Vault に実在する読み込み済み .typ ファイル
+    pub fn local_sources(&self) -> Vec<Source> {
+        let m = self.slots.lock().unwrap();
+        let local = self.local_files.lock().unwrap();
+
+        m.iter()
+            .filter(|(id, _)| local.contains(*id) || self.vault_files.contains(*id))
+            .filter(|(id, _)| {
+                id.vpath()
+                    .as_rootless_path()
+                    .extension()
+                    .is_some_and(|ext| ext == "typ")
+            })
+            .filter_map(|(_, slot)| slot.source().ok())
+            .collect()
+    }
+
+    pub fn budget(&mut self) -> &mut Budget {
+        &mut self.budget
+    }
+
     pub fn add_font(&mut self, data: Bytes) {
         for f in Font::iter(data) {
             self.book.push(f.info().clone());
@@ -231,12 +329,51 @@ impl WasmWorld {
                 ),
             };
 
+            // ? JS から読み込めたローカルファイルは Vault に実在する
+            if id.package().is_none() && result.is_ok() {
+                self.mark_local(id);
+            }
             m.insert(id, FileSlot::new_from_result(id, result));
         }
 
         f(m.get_mut(&id).unwrap())
     }
 
+    fn access(&self, id: FileId) {
+        if id.package().is_none() && id != self.main {
+            self.accessed.lock().unwrap().insert(id);
+        }
+    }
+
+    pub fn clear_accessed(&self) {
+        self.accessed.lock().unwrap().clear();
+    }
+
+    // ? main 以外に読み込まれたローカルファイルのパスとハッシュ
+    pub fn dependencies(&self) -> Vec<(String, u64)> {
+        let accessed = std::mem::take(&mut *self.accessed.lock().unwrap());
+
+        accessed
+            .into_iter()
+            .filter_map(|id| {
+                let path = id.vpath().as_rooted_path().to_str()?.to_string();
+                let hash = self.file_hash(id)?;
+                Some((path, hash))
+            })
+            .collect()
+    }
+
+    // ? 未読み込みであれば JS から取得する
+    pub fn dependency_hash(&self, path: &str) -> Option<u64> {
+        self.file_hash(FileId::new(None, VirtualPath::new(path)))
+    }
+
+    fn file_hash(&self, id: FileId) -> Option<u64> {
+        self.read(id, |f| f.bytes())
+            .ok()
+            .map(|bytes| cache::hash_of(&bytes))
+    }
+
     pub fn update_now(&mut self) {
         use chrono::TimeZone;
         let now_ms = js_sys::Date::now();
@@ -267,16 +404,21 @@ impl World for WasmWorld {
 
     // ? .typ ファイル
     fn source(&self, id: FileId) -> FileResult<Source> {
+        self.budget.check()?;
+        self.access(id);
         self.read(id, |f| f.source())
     }
 
     // ? アセットファイル (画像や wasm など)
     fn file(&self, id: FileId) -> FileResult<Bytes> {
+        self.budget.check()?;
+        self.access(id);
         self.read(id, |f| f.bytes())
     }
 
     // ? 登録されていないフォントにアクセスを試みると，Warning(severity: 2) が発生するる
     fn font(&self, index: usize) -> Option<Font> {
+        self.budget.check().ok()?;
         Some(self.fonts[index].clone())
     }
 
@@ -305,11 +447,20 @@ impl IdeWorld for WasmWorld {
         self
     }
 
-    fn packages(&self) -> &[(PackageSpec, Option<typst::ecow::EcoString>)] {
-        &[]
+    fn packages(&self) -> &[(PackageSpec, Option<EcoString>)] {
+        &self.package_list
     }
 
     fn files(&self) -> Vec<FileId> {
-        std::vec![]
+        let m = self.slots.lock().unwrap();
+
+        let mut files: FxHashSet<FileId> = m
+            .iter()
+            .filter(|(_, slot)| slot.bytes().is_ok())
+            .map(|(id, _)| *id)
+            .collect();
+        files.extend(self.vault_files.iter().copied());
+
+        files.into_iter().collect()
     }
 }